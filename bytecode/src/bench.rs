@@ -0,0 +1,258 @@
+use std::{
+    io::{self, Write},
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use treewalk::{interpreter::Interpreter, lox::Lox};
+
+use crate::{exec::vm::VirtualMachine, repr::error::LoxError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Engine {
+    Bytecode,
+    Treewalk,
+}
+
+impl Engine {
+    pub fn name(self) -> &'static str {
+        match self {
+            Engine::Bytecode => "bytecode",
+            Engine::Treewalk => "treewalk",
+        }
+    }
+
+    /// What the engine's work counter measures
+    pub fn counter(self) -> &'static str {
+        match self {
+            Engine::Bytecode => "instructions",
+            Engine::Treewalk => "steps",
+        }
+    }
+
+    /// Scripts can restrict the engines they run on with a leading `// engines: a, b` comment
+    pub fn supports(self, source: &str) -> bool {
+        let engines = source
+            .lines()
+            .take_while(|line| line.starts_with("//"))
+            .find_map(|line| line.trim_start_matches('/').trim().strip_prefix("engines:"));
+
+        match engines {
+            Some(engines) => engines.split(',').any(|name| name.trim() == self.name()),
+            None => true,
+        }
+    }
+}
+
+impl FromStr for Engine {
+    type Err = LoxError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "bytecode" => Ok(Engine::Bytecode),
+            "treewalk" => Ok(Engine::Treewalk),
+            _ => Err(LoxError::IncorrectArgumentsError),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Stats {
+    mean: Duration,
+    median: Duration,
+    stddev: Duration,
+    /// Work done by a single run, in the unit named by [`Engine::counter`]
+    count: u64,
+}
+
+impl Stats {
+    fn new(mut samples: Vec<Duration>, count: u64) -> Self {
+        samples.sort();
+
+        let len = samples.len() as f64;
+        let mean = samples.iter().map(Duration::as_secs_f64).sum::<f64>() / len;
+        let variance = samples
+            .iter()
+            .map(|sample| (sample.as_secs_f64() - mean).powi(2))
+            .sum::<f64>()
+            / len;
+
+        let middle = samples.len() / 2;
+        let median = if samples.len().is_multiple_of(2) {
+            (samples[middle - 1] + samples[middle]) / 2
+        } else {
+            samples[middle]
+        };
+
+        Stats {
+            mean: Duration::from_secs_f64(mean),
+            median,
+            stddev: Duration::from_secs_f64(variance.sqrt()),
+            count,
+        }
+    }
+}
+
+pub enum Outcome {
+    Measured(Stats),
+    Skipped,
+    Failed(String),
+}
+
+/// Runs `source` from a fresh interpreter `iterations` times, discarding what it prints
+pub fn measure(
+    source: &str,
+    engine: Engine,
+    iterations: usize,
+    optimize: bool,
+) -> Result<Stats, String> {
+    let mut samples = Vec::with_capacity(iterations);
+    let mut count = 0;
+
+    for _ in 0..iterations {
+        let start = Instant::now();
+
+        match engine {
+            Engine::Bytecode => {
+                let mut vm = VirtualMachine::with_streams(io::sink(), io::stderr(), io::empty());
+                vm.set_optimize(optimize);
+                vm.interpret(source).map_err(|error| error.to_string())?;
+                count = vm.instructions();
+            }
+
+            Engine::Treewalk => {
+                let mut interpreter =
+                    Interpreter::with_streams(io::sink(), io::stderr(), io::empty());
+                if !Lox::run(source.to_string(), &mut interpreter) {
+                    return Err(String::from("Script reported errors"));
+                }
+                count = interpreter.steps();
+            }
+        }
+
+        samples.push(start.elapsed());
+    }
+
+    Ok(Stats::new(samples, count))
+}
+
+pub struct Report {
+    engine: Engine,
+    iterations: usize,
+    optimize: bool,
+    results: Vec<(String, Outcome)>,
+}
+
+impl Report {
+    pub fn new(engine: Engine, iterations: usize, optimize: bool) -> Self {
+        Report {
+            engine,
+            iterations,
+            optimize,
+            results: vec![],
+        }
+    }
+
+    pub fn push(&mut self, script: &str, outcome: Outcome) {
+        self.results.push((script.to_string(), outcome));
+    }
+
+    pub fn write_text<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let width = self
+            .results
+            .iter()
+            .map(|(script, _)| script.chars().count())
+            .fold("script".len(), usize::max);
+
+        let optimize = if self.optimize { ", optimized" } else { "" };
+        writeln!(
+            out,
+            "{} engine, {} iterations{optimize}",
+            self.engine.name(),
+            self.iterations
+        )?;
+        writeln!(
+            out,
+            "{:<width$} {:>12} {:>12} {:>12} {:>14}",
+            "script",
+            "mean",
+            "median",
+            "stddev",
+            self.engine.counter()
+        )?;
+
+        for (script, outcome) in &self.results {
+            match outcome {
+                Outcome::Measured(stats) => writeln!(
+                    out,
+                    "{script:<width$} {:>12.2?} {:>12.2?} {:>12.2?} {:>14}",
+                    stats.mean, stats.median, stats.stddev, stats.count
+                )?,
+
+                Outcome::Skipped => writeln!(
+                    out,
+                    "{script:<width$} skipped: not supported by the {} engine",
+                    self.engine.name()
+                )?,
+
+                Outcome::Failed(error) => writeln!(out, "{script:<width$} failed: {error}")?,
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn write_json<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        write!(
+            out,
+            "{{\"engine\":\"{}\",\"iterations\":{},\"optimize\":{},\"results\":[",
+            self.engine.name(),
+            self.iterations,
+            self.optimize
+        )?;
+
+        for (index, (script, outcome)) in self.results.iter().enumerate() {
+            if index > 0 {
+                write!(out, ",")?;
+            }
+
+            write!(out, "{{\"script\":{}", json_string(script))?;
+            match outcome {
+                Outcome::Measured(stats) => write!(
+                    out,
+                    ",\"mean_ns\":{},\"median_ns\":{},\"stddev_ns\":{},\"ops\":{}",
+                    stats.mean.as_nanos(),
+                    stats.median.as_nanos(),
+                    stats.stddev.as_nanos(),
+                    stats.count
+                )?,
+
+                Outcome::Skipped => write!(out, ",\"skipped\":true")?,
+
+                Outcome::Failed(error) => write!(out, ",\"error\":{}", json_string(error))?,
+            }
+            write!(out, "}}")?;
+        }
+
+        writeln!(out, "]}}")
+    }
+}
+
+fn json_string(string: &str) -> String {
+    let mut escaped = String::with_capacity(string.len() + 2);
+    escaped.push('"');
+
+    for c in string.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped.push('"');
+    escaped
+}
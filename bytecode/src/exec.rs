@@ -1,3 +1,9 @@
 pub mod compiler;
+pub mod convert;
+pub mod heap;
+pub mod natives;
+pub mod optimizer;
 pub mod scanner;
+pub mod serializer;
+pub mod verifier;
 pub mod vm;
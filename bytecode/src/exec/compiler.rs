@@ -1,87 +1,194 @@
+use std::{cmp::Ordering, rc::Rc};
+
 use crate::repr::{
-    chunk::Chunk,
-    error::{LoxError, LoxResult},
+    chunk::{Chunk, Location},
+    error::{Diagnostic, LoxError, LoxResult, Offender, Severity},
+    function::Function,
+    object::{ObjRef, Object},
     opcode::Instruction,
     precedence::{ParseFn, Precedence, Rule},
-    token::{Token, TokenType},
+    token::{Span, Token, TokenType},
     value::Value,
 };
 
-use super::scanner::Scanner;
+use super::{heap::Heap, scanner::Scanner};
 
 struct LocalSlot {
     name: String,
     depth: isize,
+    captured: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct UpvalueSlot {
+    index: u8,
+    local: bool,
 }
 
 const LOCALS_MAX: usize = crate::U8_COUNT;
+const CONSTANTS_MAX: usize = (1 << 24) - 1;
+const GLOBALS_MAX: usize = (1 << 24) - 1;
 const LOCAL_INIT: LocalSlot = LocalSlot {
     name: String::new(),
     depth: -2,
+    captured: false,
 };
 
-pub struct Compiler {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FunctionKind {
+    Function,
+    Initializer,
+    Method,
+    Script,
+}
+
+struct FunctionCompiler {
+    function: Function,
+    kind: FunctionKind,
+
+    locals: [LocalSlot; LOCALS_MAX],
+    local_count: usize,
+    upvalues: Vec<UpvalueSlot>,
+    scope_depth: isize,
+
+    /// How much code there was when each constant was added
+    constant_offsets: Vec<usize>,
+}
+
+impl FunctionCompiler {
+    fn new(kind: FunctionKind, name: Option<String>) -> Self {
+        let mut locals = [LOCAL_INIT; LOCALS_MAX];
+
+        // Slot zero is reserved for the function being called, or the receiver in methods
+        locals[0].depth = 0;
+        if let FunctionKind::Method | FunctionKind::Initializer = kind {
+            locals[0].name = String::from("this");
+        }
+
+        FunctionCompiler {
+            function: Function::new(name),
+            kind,
+
+            locals,
+            local_count: 1,
+            upvalues: vec![],
+            scope_depth: 0,
+
+            constant_offsets: vec![],
+        }
+    }
+}
+
+pub struct Compiler<'h> {
+    heap: &'h mut Heap,
+    source: Rc<str>,
     scanner: Scanner,
     previous: Token,
     current: Token,
 
-    had_error: bool,
+    diagnostics: Vec<Diagnostic>,
     panic_mode: bool,
 
-    chunk: Chunk,
+    optimize: bool,
+    operand_start: usize,
 
-    locals: [LocalSlot; LOCALS_MAX],
-    local_count: usize,
-    scope_depth: isize,
+    functions: Vec<FunctionCompiler>,
+    class_depth: usize,
 }
 
-impl Compiler {
-    pub fn new(source: &str) -> Self {
+impl<'h> Compiler<'h> {
+    pub fn new(source: &str, heap: &'h mut Heap) -> Self {
         let scanner = Scanner::new(source);
         let previous = Token::default();
         let current = Token::default();
 
         Compiler {
+            heap,
+            source: Rc::from(source),
             scanner,
             current,
             previous,
 
-            had_error: false,
+            diagnostics: vec![],
             panic_mode: false,
 
-            chunk: Chunk::new(),
+            optimize: false,
+            operand_start: 0,
 
-            locals: [LOCAL_INIT; LOCALS_MAX],
-            local_count: 0,
-            scope_depth: 0,
+            functions: vec![FunctionCompiler::new(FunctionKind::Script, None)],
+            class_depth: 0,
         }
     }
 
-    pub fn compile(&mut self) -> LoxResult<Chunk> {
+    pub fn set_optimize(&mut self, optimize: bool) {
+        self.optimize = optimize;
+    }
+
+    pub fn compile(&mut self) -> LoxResult<ObjRef> {
         self.advance();
 
         while !self.catch(TokenType::Eof) {
             self.declaration();
         }
 
-        if self.had_error {
-            return Err(LoxError::CompileError);
+        let (function, _) = self.end_function();
+
+        let had_error = self
+            .diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.severity() == Severity::Error);
+
+        if had_error {
+            return Err(LoxError::CompileError(std::mem::take(
+                &mut self.diagnostics,
+            )));
         }
 
-        self.finish();
+        Ok(function)
+    }
+
+    fn end_function(&mut self) -> (ObjRef, Vec<UpvalueSlot>) {
+        self.emit_return();
 
-        let chunk = self.get_chunk();
+        let compiler = self
+            .functions
+            .pop()
+            .expect("Compiler Error — Function stack underflow");
+        let mut function = compiler.function;
+        function.set_upvalue_count(compiler.upvalues.len());
+        function.set_source(Rc::clone(&self.source));
 
-        #[cfg(debug_assertions)]
-        if !self.had_error {
-            chunk.disassemble("code");
-        }
+        let function = self.heap.alloc(Object::Function(function));
+        (function, compiler.upvalues)
+    }
+
+    fn current(&self) -> &FunctionCompiler {
+        self.functions
+            .last()
+            .expect("Compiler Error — No function being compiled")
+    }
+
+    fn current_mut(&mut self) -> &mut FunctionCompiler {
+        self.functions
+            .last_mut()
+            .expect("Compiler Error — No function being compiled")
+    }
 
-        Ok(chunk)
+    fn chunk(&mut self) -> &mut Chunk {
+        self.current_mut().function.chunk_mut()
     }
 
-    fn get_chunk(&mut self) -> Chunk {
-        std::mem::take(&mut self.chunk)
+    /// Drops the code from `start` on, along with the constants only that code used
+    fn truncate(&mut self, start: usize) {
+        let current = self.current_mut();
+        let kept = current
+            .constant_offsets
+            .partition_point(|&offset| offset < start);
+        current.constant_offsets.truncate(kept);
+
+        let chunk = current.function.chunk_mut();
+        chunk.truncate(start);
+        chunk.truncate_constants(kept);
     }
 
     fn advance(&mut self) {
@@ -120,7 +227,11 @@ impl Compiler {
     }
 
     fn declaration(&mut self) {
-        if self.catch(TokenType::Var) {
+        if self.catch(TokenType::Class) {
+            self.class_declaration();
+        } else if self.catch(TokenType::Fun) {
+            self.fun_declaration();
+        } else if self.catch(TokenType::Var) {
             self.var_declaration();
         } else {
             self.statement();
@@ -131,6 +242,97 @@ impl Compiler {
         }
     }
 
+    fn class_declaration(&mut self) {
+        self.consume(TokenType::Identifier, "Expect class name.");
+        let class_name = self.previous.lexeme();
+        let name_constant = self.indentifier_constant(class_name.clone());
+        self.declare_variable();
+        let global = if self.current().scope_depth > 0 {
+            0
+        } else {
+            self.global_slot(class_name.clone())
+        };
+
+        self.emit_operand(Instruction::Class, name_constant);
+        self.define_variable(global);
+
+        self.class_depth += 1;
+
+        self.named_variable(class_name, false);
+        self.consume(TokenType::LeftBrace, "Expect '{' before class body.");
+        while !self.check(TokenType::RightBrace) && !self.check(TokenType::Eof) {
+            self.method();
+        }
+        self.consume(TokenType::RightBrace, "Expect '}' after class body.");
+        self.emit(Instruction::Pop);
+
+        self.class_depth -= 1;
+    }
+
+    fn method(&mut self) {
+        self.consume(TokenType::Identifier, "Expect method name.");
+        let name = self.previous.lexeme();
+        let constant = self.indentifier_constant(name.clone());
+
+        let kind = if name == "init" {
+            FunctionKind::Initializer
+        } else {
+            FunctionKind::Method
+        };
+        self.function(kind);
+
+        self.emit_operand(Instruction::Method, constant);
+    }
+
+    fn fun_declaration(&mut self) {
+        let global = self.parse_variable("Expect function name.");
+        self.initialize();
+        self.function(FunctionKind::Function);
+        self.define_variable(global);
+    }
+
+    fn function(&mut self, kind: FunctionKind) {
+        let name = self.previous.lexeme();
+        if self.functions.len() > crate::FUNCTION_DEPTH_MAX {
+            self.error("Too many nested functions.");
+        }
+        self.functions.push(FunctionCompiler::new(kind, Some(name)));
+        self.begin_scope();
+
+        self.consume(TokenType::LeftParen, "Expect '(' after function name.");
+
+        let mut arity = 0;
+        if !self.check(TokenType::RightParen) {
+            loop {
+                arity += 1;
+                if arity > u8::MAX as usize {
+                    self.error_current("Can't have more than 255 parameters.");
+                }
+
+                let constant = self.parse_variable("Expect parameter name.");
+                self.define_variable(constant);
+
+                if !self.catch(TokenType::Comma) {
+                    break;
+                }
+            }
+        }
+        self.current_mut().function.set_arity(arity);
+
+        self.consume(TokenType::RightParen, "Expect ')' after parameters.");
+        self.consume(TokenType::LeftBrace, "Expect '{' before function body.");
+        self.block();
+
+        let (function, upvalues) = self.end_function();
+        let constant = self.make_constant(Value::Object(function));
+        self.emit_operand(Instruction::Closure, constant);
+
+        for upvalue in upvalues {
+            self.emit_byte(upvalue.local as u8);
+            self.emit_byte(upvalue.index);
+        }
+    }
+
     fn var_declaration(&mut self) {
         let global = self.parse_variable("Expect variable name");
 
@@ -148,30 +350,50 @@ impl Compiler {
         self.define_variable(global);
     }
 
-    fn parse_variable(&mut self, message: &str) -> u8 {
+    fn parse_variable(&mut self, message: &str) -> usize {
         self.consume(TokenType::Identifier, message);
 
         self.declare_variable();
+        if self.current().scope_depth > 0 {
+            return 0;
+        }
 
-        self.indentifier_constant(self.previous.lexeme())
+        self.global_slot(self.previous.lexeme())
     }
 
-    fn indentifier_constant(&mut self, name: String) -> u8 {
-        self.make_constant(Value::String(Box::new(name)))
+    fn indentifier_constant(&mut self, name: String) -> usize {
+        let name = self.heap.alloc_string(name);
+        self.make_constant(name)
     }
 
-    fn define_variable(&mut self, global: u8) {
-        if self.scope_depth > 0 {
+    fn global_slot(&mut self, name: String) -> usize {
+        let name = self.heap.intern(name);
+        let slot = self.heap.global_slot(name);
+
+        if slot > GLOBALS_MAX {
+            self.error("Too many global variables.");
+            return 0;
+        }
+
+        slot
+    }
+
+    fn define_variable(&mut self, global: usize) {
+        if self.current().scope_depth > 0 {
             self.initialize();
             return;
         }
 
-        self.emit(Instruction::DefineGlobal);
-        self.emit_byte(global);
+        self.emit_operand(Instruction::DefineGlobal, global);
     }
 
     fn initialize(&mut self) {
-        self.locals[self.local_count - 1].depth = self.scope_depth;
+        let current = self.current_mut();
+        if current.scope_depth == 0 {
+            return;
+        }
+
+        current.locals[current.local_count - 1].depth = current.scope_depth;
     }
 
     fn statement(&mut self) {
@@ -181,6 +403,8 @@ impl Compiler {
             self.for_statement();
         } else if self.catch(TokenType::If) {
             self.if_statement();
+        } else if self.catch(TokenType::Return) {
+            self.return_statement();
         } else if self.catch(TokenType::While) {
             self.while_statement();
         } else if self.catch(TokenType::LeftBrace) {
@@ -194,10 +418,30 @@ impl Compiler {
 
     fn if_statement(&mut self) {
         self.consume(TokenType::LeftParen, "Expect '(' after 'if'.");
+        let condition_start = self.chunk().len();
         self.expression();
         self.consume(TokenType::RightParen, "Expect ')' after condition.");
 
+        if let Some(condition) = self.constant_condition(condition_start) {
+            let then_start = self.chunk().len();
+            self.statement();
+            if !condition {
+                self.truncate(then_start);
+            }
+
+            if self.catch(TokenType::Else) {
+                let else_start = self.chunk().len();
+                self.statement();
+                if condition {
+                    self.truncate(else_start);
+                }
+            }
+
+            return;
+        }
+
         let then_jump = self.emit_jump(Instruction::JumpIfFalse);
+        self.emit(Instruction::Pop);
         self.statement();
 
         let else_jump = self.emit_jump(Instruction::Jump);
@@ -212,13 +456,42 @@ impl Compiler {
         self.patch_jump(else_jump);
     }
 
+    fn return_statement(&mut self) {
+        if self.current().kind == FunctionKind::Script {
+            self.error("Can't return from top-level code.");
+        }
+
+        if self.catch(TokenType::Semicolon) {
+            self.emit_return();
+        } else {
+            if self.current().kind == FunctionKind::Initializer {
+                self.error("Can't return a value from an initializer.");
+            }
+
+            self.expression();
+            self.consume(TokenType::Semicolon, "Expect ';' after return value.");
+            self.emit(Instruction::Return);
+        }
+    }
+
     fn while_statement(&mut self) {
-        let loop_start = self.chunk.len();
+        let loop_start = self.chunk().len();
 
         self.consume(TokenType::LeftParen, "Expect '(' after 'while'.");
         self.expression();
         self.consume(TokenType::RightParen, "Expect ')' after condition.");
 
+        if let Some(condition) = self.constant_condition(loop_start) {
+            self.statement();
+            if condition {
+                self.emit_loop(loop_start);
+            } else {
+                self.truncate(loop_start);
+            }
+
+            return;
+        }
+
         let exit_jump = self.emit_jump(Instruction::JumpIfFalse);
         self.emit(Instruction::Pop);
         self.statement();
@@ -231,7 +504,6 @@ impl Compiler {
     fn for_statement(&mut self) {
         self.begin_scope();
         self.consume(TokenType::LeftParen, "Expect '(' after 'for'.");
-        // self.consume(TokenType::Semicolon, "Expect ';'.");
         if self.catch(TokenType::Semicolon) {
             // No initializer
         } else if self.catch(TokenType::Var) {
@@ -240,21 +512,26 @@ impl Compiler {
             self.expression_statement();
         }
 
-        let mut loop_start = self.chunk.len();
-        let exit_jump: Option<usize>;
+        let condition_start = self.chunk().len();
+        let mut loop_start = condition_start;
+        let mut exit_jump = None;
+        let mut dead = false;
         if !self.catch(TokenType::Semicolon) {
             self.expression();
             self.consume(TokenType::Semicolon, "Expect ';' after loop condition.");
 
-            exit_jump = Some(self.emit_jump(Instruction::JumpIfFalse));
-            self.emit(Instruction::Pop);
-        } else {
-            exit_jump = None;
+            match self.constant_condition(condition_start) {
+                Some(condition) => dead = !condition,
+                None => {
+                    exit_jump = Some(self.emit_jump(Instruction::JumpIfFalse));
+                    self.emit(Instruction::Pop);
+                }
+            }
         }
 
         if !self.catch(TokenType::RightParen) {
             let body_jump = self.emit_jump(Instruction::Jump);
-            let increment_start = self.chunk.len();
+            let increment_start = self.chunk().len();
             self.expression();
             self.emit(Instruction::Pop);
             self.consume(TokenType::RightParen, "Expect ')' after for clauses.");
@@ -267,6 +544,10 @@ impl Compiler {
         self.statement();
         self.emit_loop(loop_start);
 
+        if dead {
+            self.truncate(condition_start);
+        }
+
         if let Some(exit_jump) = exit_jump {
             self.patch_jump(exit_jump);
             self.emit(Instruction::Pop);
@@ -276,15 +557,26 @@ impl Compiler {
     }
 
     fn begin_scope(&mut self) {
-        self.scope_depth += 1;
+        self.current_mut().scope_depth += 1;
     }
 
     fn end_scope(&mut self) {
-        self.scope_depth -= 1;
+        self.current_mut().scope_depth -= 1;
 
-        while self.local_count > 0 && self.locals[self.local_count - 1].depth > self.scope_depth {
-            self.emit(Instruction::Pop);
-            self.local_count -= 1;
+        loop {
+            let current = self.current();
+            if current.local_count == 0
+                || current.locals[current.local_count - 1].depth <= current.scope_depth
+            {
+                break;
+            }
+
+            if current.locals[current.local_count - 1].captured {
+                self.emit(Instruction::CloseUpvalue);
+            } else {
+                self.emit(Instruction::Pop);
+            }
+            self.current_mut().local_count -= 1;
         }
     }
 
@@ -297,52 +589,85 @@ impl Compiler {
     }
 
     fn declare_variable(&mut self) {
-        if self.scope_depth == 0 {
+        if self.current().scope_depth == 0 {
             return;
         }
 
         let name = self.previous.lexeme();
 
-        for i in (0..self.local_count).rev() {
-            let local = &self.locals[i];
-
-            if local.depth != -1 && local.depth < self.scope_depth {
-                break;
-            }
+        let current = self.current();
+        let duplicate = current.locals[..current.local_count]
+            .iter()
+            .rev()
+            .take_while(|local| local.depth == -1 || local.depth >= current.scope_depth)
+            .any(|local| local.name == name);
 
-            if name == local.name {
-                self.error("Already a variable with this name in this scope.");
-            }
+        if duplicate {
+            self.error("Already a variable with this name in this scope.");
         }
 
         self.add_local(name);
     }
 
     fn add_local(&mut self, name: String) {
-        if self.local_count == LOCALS_MAX {
+        if self.current().local_count == LOCALS_MAX {
             self.error("Too many local variables in function.");
             return;
         }
 
-        let local = &mut self.locals[self.local_count];
-        self.local_count += 1;
+        let current = self.current_mut();
+        let local = &mut current.locals[current.local_count];
+        current.local_count += 1;
 
         local.name = name;
         local.depth = -1;
+        local.captured = false;
     }
 
-    fn resolve_local(&mut self, name: &String) -> Option<u8> {
-        for i in (0..self.local_count).rev() {
-            let local = &self.locals[i];
-            if name == &local.name {
-                if local.depth == -1 {
-                    self.error("Can't read local variable in its own initializer.");
-                }
-                return Some(i as u8);
-            }
+    fn resolve_local(&mut self, compiler: usize, name: &String) -> Option<u8> {
+        let function = &self.functions[compiler];
+        let (slot, depth) = function.locals[..function.local_count]
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|(_, local)| name == &local.name)
+            .map(|(slot, local)| (slot, local.depth))?;
+
+        if depth == -1 {
+            self.error("Can't read local variable in its own initializer.");
+        }
+
+        Some(slot as u8)
+    }
+
+    fn resolve_upvalue(&mut self, compiler: usize, name: &String) -> Option<u8> {
+        let enclosing = compiler.checked_sub(1)?;
+
+        if let Some(local) = self.resolve_local(enclosing, name) {
+            self.functions[enclosing].locals[local as usize].captured = true;
+            return Some(self.add_upvalue(compiler, local, true));
         }
 
-        None
+        let upvalue = self.resolve_upvalue(enclosing, name)?;
+        Some(self.add_upvalue(compiler, upvalue, false))
+    }
+
+    fn add_upvalue(&mut self, compiler: usize, index: u8, local: bool) -> u8 {
+        let upvalue = UpvalueSlot { index, local };
+        let upvalues = &self.functions[compiler].upvalues;
+
+        if let Some(existing) = upvalues.iter().position(|slot| *slot == upvalue) {
+            return existing as u8;
+        }
+
+        if upvalues.len() == crate::U8_COUNT {
+            self.error("Too many closure variables in function.");
+            return 0;
+        }
+
+        let upvalues = &mut self.functions[compiler].upvalues;
+        upvalues.push(upvalue);
+        (upvalues.len() - 1) as u8
     }
 
     fn synchronize(&mut self) {
@@ -398,13 +723,70 @@ impl Compiler {
     }
 
     fn string(&mut self) {
-        let value = Value::String(Box::new(self.previous.lexeme()));
+        let value = self.heap.alloc_string(self.previous.lexeme());
         self.emit_constant(value);
     }
 
+    fn call(&mut self) {
+        let start = self.previous.span();
+        let argc = self.argument_list();
+
+        let span = start.to(self.previous.span());
+        self.emit_at(Instruction::Call, span);
+        self.emit_byte(argc);
+    }
+
+    fn argument_list(&mut self) -> u8 {
+        let mut argc = 0;
+        if !self.check(TokenType::RightParen) {
+            loop {
+                self.expression();
+                if argc == u8::MAX as usize {
+                    self.error("Can't have more than 255 arguments.");
+                }
+                argc += 1;
+
+                if !self.catch(TokenType::Comma) {
+                    break;
+                }
+            }
+        }
+
+        self.consume(TokenType::RightParen, "Expect ')' after arguments.");
+        argc as u8
+    }
+
+    fn dot(&mut self, assign: bool) {
+        self.consume(TokenType::Identifier, "Expect property name after '.'.");
+        let span = self.previous.span();
+        let name = self.indentifier_constant(self.previous.lexeme());
+
+        if assign && self.catch(TokenType::Equal) {
+            self.expression();
+            self.emit_operand_at(Instruction::SetProperty, name, span);
+        } else {
+            self.emit_operand_at(Instruction::GetProperty, name, span);
+        }
+    }
+
+    fn this(&mut self) {
+        if self.class_depth == 0 {
+            self.error("Can't use 'this' outside of a class.");
+            return;
+        }
+
+        self.variable(false);
+    }
+
     fn and(&mut self) {
+        let left_start = self.operand_start;
+        if let Some(left) = self.constant(left_start) {
+            self.short_circuit(left_start, !left.truthy(), Precedence::And);
+            return;
+        }
+
         let end_jump = self.emit_jump(Instruction::JumpIfFalse);
-        
+
         self.emit(Instruction::Pop);
         self.precedence(Precedence::And);
 
@@ -412,6 +794,12 @@ impl Compiler {
     }
 
     fn or(&mut self) {
+        let left_start = self.operand_start;
+        if let Some(left) = self.constant(left_start) {
+            self.short_circuit(left_start, left.truthy(), Precedence::Or);
+            return;
+        }
+
         let else_jump = self.emit_jump(Instruction::JumpIfFalse);
         let end_jump = self.emit_jump(Instruction::Jump);
 
@@ -422,31 +810,47 @@ impl Compiler {
         self.patch_jump(end_jump);
     }
 
+    fn short_circuit(&mut self, left_start: usize, keep_left: bool, prec: Precedence) {
+        if keep_left {
+            let right_start = self.chunk().len();
+            self.precedence(prec);
+            self.truncate(right_start);
+        } else {
+            self.truncate(left_start);
+            self.precedence(prec);
+        }
+    }
+
     fn variable(&mut self, assign: bool) {
         self.named_variable(self.previous.lexeme(), assign);
     }
 
     fn named_variable(&mut self, name: String, assign: bool) {
+        let span = self.previous.span();
         let get_op: Instruction;
         let set_op: Instruction;
 
-        let arg = if let Some(byte) = self.resolve_local(&name) {
+        let compiler = self.functions.len() - 1;
+
+        let arg = if let Some(byte) = self.resolve_local(compiler, &name) {
             get_op = Instruction::GetLocal;
             set_op = Instruction::SetLocal;
-            byte
+            byte as usize
+        } else if let Some(byte) = self.resolve_upvalue(compiler, &name) {
+            get_op = Instruction::GetUpvalue;
+            set_op = Instruction::SetUpvalue;
+            byte as usize
         } else {
             get_op = Instruction::GetGlobal;
             set_op = Instruction::SetGlobal;
-            self.indentifier_constant(name)
+            self.global_slot(name)
         };
 
         if assign && self.catch(TokenType::Equal) {
             self.expression();
-            self.emit(set_op);
-            self.emit_byte(arg);
+            self.emit_operand_at(set_op, arg, span);
         } else {
-            self.emit(get_op);
-            self.emit_byte(arg);
+            self.emit_operand_at(get_op, arg, span);
         }
     }
 
@@ -464,70 +868,217 @@ impl Compiler {
 
     fn unary(&mut self) {
         let op = self.previous.kind();
+        let span = self.previous.span();
+        let operand_start = self.chunk().len();
 
         // Compile the operand
         self.precedence(Precedence::Unary);
 
+        let folded = match (op, self.constant(operand_start)) {
+            (TokenType::Minus, Some(Value::Number(a))) => Some(Value::Number(-a)),
+            (TokenType::Bang, Some(a)) => Some(Value::Boolean(!a.truthy())),
+            _ => None,
+        };
+
+        if let Some(value) = folded {
+            self.replace_with_constant(operand_start, value);
+            return;
+        }
+
         match op {
-            TokenType::Minus => self.emit(Instruction::Negate),
-            TokenType::Bang => self.emit(Instruction::Not),
+            TokenType::Minus => self.emit_at(Instruction::Negate, span),
+            TokenType::Bang => self.emit_at(Instruction::Not, span),
 
             _ => unreachable!(),
         }
     }
 
     fn binary(&mut self) {
+        let left_start = self.operand_start;
         let operator = self.previous.kind();
+        let span = self.previous.span();
 
         let rule = Rule::from(operator);
         let prec =
             Precedence::try_from(rule.prec() as u8 + 1).expect("Failed to get next precedence");
 
+        let right_start = self.chunk().len();
         self.precedence(prec);
 
-        match operator {
-            TokenType::EqualEqual => self.emit(Instruction::Equal),
-            TokenType::BangEqual => {
-                self.emit(Instruction::Equal);
-                self.emit(Instruction::Not);
+        if let Some(value) = self.fold_binary(operator, left_start, right_start) {
+            self.replace_with_constant(left_start, value);
+            return;
+        }
+
+        let instructions: &[Instruction] = match operator {
+            TokenType::EqualEqual => &[Instruction::Equal],
+            TokenType::BangEqual => &[Instruction::Equal, Instruction::Not],
+            TokenType::Greater => &[Instruction::Greater],
+            TokenType::GreaterEqual => &[Instruction::Less, Instruction::Not],
+            TokenType::Less => &[Instruction::Less],
+            TokenType::LessEqual => &[Instruction::Greater, Instruction::Not],
+            TokenType::Plus => &[Instruction::Add],
+            TokenType::Minus => &[Instruction::Subtract],
+            TokenType::Star => &[Instruction::Multiply],
+            TokenType::Slash => &[Instruction::Divide],
+
+            _ => unreachable!(),
+        };
+
+        // Runtime errors point at the operator rather than the end of the right operand
+        for &instruction in instructions {
+            self.emit_at(instruction, span);
+        }
+    }
+
+    fn fold_binary(
+        &mut self,
+        operator: TokenType,
+        left_start: usize,
+        right_start: usize,
+    ) -> Option<Value> {
+        let left = self.constant_between(left_start, right_start)?;
+        let right = self.constant(right_start)?;
+
+        let value = match (operator, left, right) {
+            (TokenType::EqualEqual, a, b) => Value::Boolean(a == b),
+            (TokenType::BangEqual, a, b) => Value::Boolean(a != b),
+
+            (TokenType::Greater, Value::Number(a), Value::Number(b)) => Value::Boolean(a > b),
+            (TokenType::Less, Value::Number(a), Value::Number(b)) => Value::Boolean(a < b),
+            // Compiled as `Less; Not` and `Greater; Not`, so NaN has to compare the same way
+            (TokenType::GreaterEqual, Value::Number(a), Value::Number(b)) => {
+                Value::Boolean(a.partial_cmp(&b) != Some(Ordering::Less))
             }
-            TokenType::Greater => self.emit(Instruction::Greater),
-            TokenType::GreaterEqual => {
-                self.emit(Instruction::Less);
-                self.emit(Instruction::Not);
+            (TokenType::LessEqual, Value::Number(a), Value::Number(b)) => {
+                Value::Boolean(a.partial_cmp(&b) != Some(Ordering::Greater))
             }
-            TokenType::Less => self.emit(Instruction::Less),
-            TokenType::LessEqual => {
-                self.emit(Instruction::Greater);
-                self.emit(Instruction::Not);
+
+            (TokenType::Plus, Value::Number(a), Value::Number(b)) => Value::Number(a + b),
+            (TokenType::Minus, Value::Number(a), Value::Number(b)) => Value::Number(a - b),
+            (TokenType::Star, Value::Number(a), Value::Number(b)) => Value::Number(a * b),
+            (TokenType::Slash, Value::Number(a), Value::Number(b)) => Value::Number(a / b),
+
+            (TokenType::Plus, Value::Object(a), Value::Object(b))
+                if self.heap.is_string(a) && self.heap.is_string(b) =>
+            {
+                let string = format!("{}{}", self.heap.string(a), self.heap.string(b));
+                self.heap.alloc_string(string)
             }
-            TokenType::Plus => self.emit(Instruction::Add),
-            TokenType::Minus => self.emit(Instruction::Subtract),
-            TokenType::Star => self.emit(Instruction::Multiply),
-            TokenType::Slash => self.emit(Instruction::Divide),
 
-            _ => unreachable!(),
+            _ => return None,
+        };
+
+        Some(value)
+    }
+
+    fn constant_condition(&mut self, start: usize) -> Option<bool> {
+        let condition = self.constant(start)?;
+        self.truncate(start);
+        Some(condition.truthy())
+    }
+
+    fn constant(&mut self, start: usize) -> Option<Value> {
+        let end = self.chunk().len();
+        self.constant_between(start, end)
+    }
+
+    fn constant_between(&mut self, start: usize, end: usize) -> Option<Value> {
+        if !self.optimize {
+            return None;
+        }
+
+        let chunk = self.chunk();
+        let instruction = Instruction::try_from(*chunk.code().get(start)?).ok()?;
+
+        let (value, len) = match instruction {
+            Instruction::Nil => (Value::Nil, 1),
+            Instruction::True => (Value::Boolean(true), 1),
+            Instruction::False => (Value::Boolean(false), 1),
+            Instruction::Constant => (chunk.read_constant(chunk.code()[start + 1] as usize)?, 2),
+            Instruction::ConstantLong => {
+                let index = u32::from_be_bytes([
+                    0,
+                    chunk.code()[start + 1],
+                    chunk.code()[start + 2],
+                    chunk.code()[start + 3],
+                ]);
+                (chunk.read_constant(index as usize)?, 4)
+            }
+            _ => return None,
+        };
+
+        (start + len == end).then_some(value)
+    }
+
+    fn replace_with_constant(&mut self, start: usize, value: Value) {
+        self.truncate(start);
+
+        match value {
+            Value::Nil => self.emit(Instruction::Nil),
+            Value::Boolean(true) => self.emit(Instruction::True),
+            Value::Boolean(false) => self.emit(Instruction::False),
+            value => self.emit_constant(value),
         }
     }
 
     fn emit(&mut self, opcode: Instruction) {
-        self.chunk.write(opcode, self.previous.line());
+        self.emit_at(opcode, self.previous.span());
+    }
+
+    fn emit_at(&mut self, opcode: Instruction, span: Span) {
+        let width = self.source[span.start()..span.end()].chars().count();
+        let location = Location::new(span.line(), span.column(), width);
+        self.chunk().write(opcode, location);
     }
 
     fn emit_byte(&mut self, byte: u8) {
-        self.chunk.write_byte(byte, self.previous.line());
+        self.chunk().write_byte(byte);
+    }
+
+    fn emit_return(&mut self) {
+        if self.current().kind == FunctionKind::Initializer {
+            self.emit(Instruction::GetLocal);
+            self.emit_byte(0);
+        } else {
+            self.emit(Instruction::Nil);
+        }
+        self.emit(Instruction::Return);
     }
 
     fn emit_constant(&mut self, value: Value) {
         let constant = self.make_constant(value);
-        self.emit(Instruction::Constant);
-        self.emit_byte(constant);
+        self.emit_operand(Instruction::Constant, constant);
+    }
+
+    fn emit_operand(&mut self, opcode: Instruction, operand: usize) {
+        self.emit_operand_at(opcode, operand, self.previous.span());
+    }
+
+    fn emit_operand_at(&mut self, opcode: Instruction, operand: usize, span: Span) {
+        if let Ok(byte) = u8::try_from(operand) {
+            self.emit_at(opcode, span);
+            self.emit_byte(byte);
+            return;
+        }
+
+        let Some(long) = opcode.long() else {
+            self.error("Too many constants in one chunk.");
+            return;
+        };
+
+        let [_, addr_a, addr_b, addr_c] = (operand as u32).to_be_bytes();
+
+        self.emit_at(long, span);
+        self.emit_byte(addr_a);
+        self.emit_byte(addr_b);
+        self.emit_byte(addr_c);
     }
 
     fn emit_loop(&mut self, start: usize) {
         self.emit(Instruction::Loop);
 
-        let offset = self.chunk.len() - start + 2;
+        let offset = self.chunk().len() - start + 2;
         if offset > u16::MAX as usize {
             self.error("Loop body too large.");
         }
@@ -543,13 +1094,13 @@ impl Compiler {
         self.emit_byte(0xff);
         self.emit_byte(0xff);
 
-        self.chunk.len() - 2
+        self.chunk().len() - 2
     }
 
     fn patch_jump(&mut self, offset: usize) {
         // -2 to adjust for the bytecode for the jump offset itself.
 
-        let jump = self.chunk.len() - offset - 2;
+        let jump = self.chunk().len() - offset - 2;
 
         if jump > u16::MAX as usize {
             self.error("Too much code to jump over");
@@ -557,18 +1108,21 @@ impl Compiler {
 
         let [addr_a, addr_b] = (jump as u16).to_be_bytes();
 
-        self.chunk.write_byte_at(addr_a, offset); 
-        self.chunk.write_byte_at(addr_b, offset + 1);
+        self.chunk().write_byte_at(addr_a, offset);
+        self.chunk().write_byte_at(addr_b, offset + 1);
     }
 
-    fn make_constant(&mut self, value: Value) -> u8 {
-        let constant = self.chunk.add_constant(value);
-        if constant > u8::MAX as usize {
+    fn make_constant(&mut self, value: Value) -> usize {
+        let offset = self.chunk().len();
+        self.current_mut().constant_offsets.push(offset);
+
+        let constant = self.chunk().add_constant(value);
+        if constant > CONSTANTS_MAX {
             self.error("Too many constants in one chunk.");
             return 0;
         }
 
-        constant as u8
+        constant
     }
 
     fn precedence(&mut self, prec: Precedence) {
@@ -580,13 +1134,15 @@ impl Compiler {
             return;
         }
 
+        let start = self.chunk().len();
         let assign = prec as u8 <= Precedence::Assignment as u8;
         self.parse(prefix, assign);
 
         while prec as u8 <= Rule::from(self.current.kind()).prec() as u8 {
             self.advance();
             let infix = Rule::from(self.previous.kind()).infix();
-            self.parse(infix, false);
+            self.operand_start = start;
+            self.parse(infix, assign);
         }
 
         if assign && self.catch(TokenType::Equal) {
@@ -604,44 +1160,42 @@ impl Compiler {
             ParseFn::String => self.string(),
             ParseFn::And => self.and(),
             ParseFn::Or => self.or(),
+            ParseFn::Call => self.call(),
+            ParseFn::Dot => self.dot(assign),
+            ParseFn::This => self.this(),
             ParseFn::Variable => self.variable(assign),
             ParseFn::Null => (),
         }
     }
 
-    fn finish(&mut self) {
-        self.emit(Instruction::Return);
-    }
-
-    fn error_at(&mut self, line: usize, kind: TokenType, lexeme: String, message: &str) {
+    fn error_at(&mut self, span: Span, kind: TokenType, lexeme: String, message: &str) {
         if self.panic_mode {
             return;
         }
         self.panic_mode = true;
 
-        eprint!("[line {}] Error", line);
-
-        match kind {
-            TokenType::Eof => eprint!(" at end"),
-            TokenType::Error => (),
-            _ => eprint!(" at '{}'", lexeme),
-        }
+        let offender = match kind {
+            TokenType::Eof => Offender::End,
+            TokenType::Error => Offender::None,
+            _ => Offender::Lexeme(lexeme),
+        };
 
-        eprintln!(": {}", message);
-        self.had_error = true;
+        let diagnostic = Diagnostic::new(Severity::Error, span, offender, message.to_string())
+            .with_source(&self.source);
+        self.diagnostics.push(diagnostic);
     }
 
     fn error(&mut self, message: &str) {
-        let line = self.previous.line();
+        let span = self.previous.span();
         let kind = self.previous.kind();
         let lexeme = self.previous.lexeme();
-        self.error_at(line, kind, lexeme, message);
+        self.error_at(span, kind, lexeme, message);
     }
 
     fn error_current(&mut self, message: &str) {
-        let line = self.current.line();
+        let span = self.current.span();
         let kind = self.current.kind();
         let lexeme = self.current.lexeme();
-        self.error_at(line, kind, lexeme, message);
+        self.error_at(span, kind, lexeme, message);
     }
 }
@@ -0,0 +1,230 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use crate::repr::{object::ObjRef, value::Value};
+
+use super::heap::Heap;
+
+/// How many [`Handle`]s refer to each object the host holds on to
+pub(crate) type Roots = Rc<RefCell<HashMap<ObjRef, usize>>>;
+
+/// Rust values that can be handed to a script
+pub trait IntoValue {
+    fn into_value(self, heap: &mut Heap) -> Value;
+}
+
+/// Rust values that can be read back out of a script
+pub trait FromValue: Sized {
+    /// What the conversion expects, for error messages
+    const EXPECTED: &'static str;
+
+    fn from_value(value: Value, heap: &Heap) -> Option<Self>;
+}
+
+/// Argument lists for calling into a script, as a tuple or a `Vec`
+pub trait IntoArgs {
+    fn into_args(self, heap: &mut Heap) -> Vec<Value>;
+}
+
+/// A script value held by the host. The collector keeps it alive until every clone is dropped
+pub struct Handle {
+    value: Value,
+    roots: Roots,
+}
+
+impl Handle {
+    pub(crate) fn new(value: Value, roots: &Roots) -> Self {
+        if let Value::Object(object) = value {
+            *roots.borrow_mut().entry(object).or_insert(0) += 1;
+        }
+
+        Handle {
+            value,
+            roots: Rc::clone(roots),
+        }
+    }
+
+    pub(crate) fn value(&self) -> Value {
+        self.value
+    }
+}
+
+impl Clone for Handle {
+    fn clone(&self) -> Self {
+        Handle::new(self.value, &self.roots)
+    }
+}
+
+impl Drop for Handle {
+    fn drop(&mut self) {
+        let Value::Object(object) = self.value else {
+            return;
+        };
+
+        let mut roots = self.roots.borrow_mut();
+        if let Some(count) = roots.get_mut(&object) {
+            *count -= 1;
+            if *count == 0 {
+                roots.remove(&object);
+            }
+        }
+    }
+}
+
+/// A function to call, either by the name of a global or through a handle
+pub enum Callee<'a> {
+    Global(&'a str),
+    Handle(&'a Handle),
+}
+
+impl<'a> From<&'a str> for Callee<'a> {
+    fn from(name: &'a str) -> Self {
+        Callee::Global(name)
+    }
+}
+
+impl<'a> From<&'a Handle> for Callee<'a> {
+    fn from(handle: &'a Handle) -> Self {
+        Callee::Handle(handle)
+    }
+}
+
+impl IntoValue for &Handle {
+    fn into_value(self, _heap: &mut Heap) -> Value {
+        self.value
+    }
+}
+
+impl IntoValue for f64 {
+    fn into_value(self, _heap: &mut Heap) -> Value {
+        Value::Number(self)
+    }
+}
+
+impl IntoValue for i32 {
+    fn into_value(self, _heap: &mut Heap) -> Value {
+        Value::Number(self.into())
+    }
+}
+
+impl IntoValue for bool {
+    fn into_value(self, _heap: &mut Heap) -> Value {
+        Value::Boolean(self)
+    }
+}
+
+impl IntoValue for () {
+    fn into_value(self, _heap: &mut Heap) -> Value {
+        Value::Nil
+    }
+}
+
+impl IntoValue for &str {
+    fn into_value(self, heap: &mut Heap) -> Value {
+        heap.alloc_string(self)
+    }
+}
+
+impl IntoValue for String {
+    fn into_value(self, heap: &mut Heap) -> Value {
+        heap.alloc_string(self)
+    }
+}
+
+impl<T: IntoValue> IntoValue for Option<T> {
+    fn into_value(self, heap: &mut Heap) -> Value {
+        match self {
+            Some(value) => value.into_value(heap),
+            None => Value::Nil,
+        }
+    }
+}
+
+impl FromValue for Handle {
+    const EXPECTED: &'static str = "a value";
+
+    fn from_value(value: Value, heap: &Heap) -> Option<Self> {
+        Some(heap.root(value))
+    }
+}
+
+impl FromValue for f64 {
+    const EXPECTED: &'static str = "a number";
+
+    fn from_value(value: Value, _heap: &Heap) -> Option<Self> {
+        match value {
+            Value::Number(number) => Some(number),
+            _ => None,
+        }
+    }
+}
+
+impl FromValue for bool {
+    const EXPECTED: &'static str = "a boolean";
+
+    fn from_value(value: Value, _heap: &Heap) -> Option<Self> {
+        match value {
+            Value::Boolean(boolean) => Some(boolean),
+            _ => None,
+        }
+    }
+}
+
+impl FromValue for () {
+    const EXPECTED: &'static str = "nil";
+
+    fn from_value(value: Value, _heap: &Heap) -> Option<Self> {
+        matches!(value, Value::Nil).then_some(())
+    }
+}
+
+impl FromValue for String {
+    const EXPECTED: &'static str = "a string";
+
+    fn from_value(value: Value, heap: &Heap) -> Option<Self> {
+        match value {
+            Value::Object(object) if heap.is_string(object) => {
+                Some(heap.string(object).to_string())
+            }
+            _ => None,
+        }
+    }
+}
+
+impl<T: FromValue> FromValue for Option<T> {
+    const EXPECTED: &'static str = T::EXPECTED;
+
+    fn from_value(value: Value, heap: &Heap) -> Option<Self> {
+        match value {
+            Value::Nil => Some(None),
+            value => T::from_value(value, heap).map(Some),
+        }
+    }
+}
+
+impl<T: IntoValue> IntoArgs for Vec<T> {
+    fn into_args(self, heap: &mut Heap) -> Vec<Value> {
+        self.into_iter()
+            .map(|argument| argument.into_value(heap))
+            .collect()
+    }
+}
+
+macro_rules! tuple_args {
+    ($($arg:ident),*) => {
+        impl<$($arg: IntoValue),*> IntoArgs for ($($arg,)*) {
+            #[allow(non_snake_case, unused_variables)]
+            fn into_args(self, heap: &mut Heap) -> Vec<Value> {
+                let ($($arg,)*) = self;
+                vec![$($arg.into_value(heap)),*]
+            }
+        }
+    };
+}
+
+tuple_args!();
+tuple_args!(A);
+tuple_args!(A, B);
+tuple_args!(A, B, C);
+tuple_args!(A, B, C, D);
+tuple_args!(A, B, C, D, E);
+tuple_args!(A, B, C, D, E, F);
@@ -0,0 +1,398 @@
+use std::{collections::HashMap, fmt::Display};
+
+use super::convert::{Handle, Roots};
+
+use crate::repr::{
+    class::{Class, Instance},
+    closure::{Closure, Upvalue},
+    function::Function,
+    object::{ObjRef, Object},
+    value::Value,
+};
+
+const GC_INITIAL_THRESHOLD: usize = 1024 * 1024;
+const GC_GROW_FACTOR: usize = 2;
+
+pub struct Heap {
+    objects: Vec<Option<Object>>,
+    marks: Vec<bool>,
+    free: Vec<usize>,
+    gray: Vec<ObjRef>,
+    strings: HashMap<String, ObjRef>,
+    globals: Vec<ObjRef>,
+    global_slots: HashMap<ObjRef, usize>,
+    roots: Roots,
+
+    bytes_allocated: usize,
+    next_gc: usize,
+    grow_factor: usize,
+    stress: bool,
+}
+
+impl Heap {
+    pub fn new() -> Self {
+        Heap {
+            objects: vec![],
+            marks: vec![],
+            free: vec![],
+            gray: vec![],
+            strings: HashMap::new(),
+            globals: vec![],
+            global_slots: HashMap::new(),
+            roots: Roots::default(),
+
+            bytes_allocated: 0,
+            next_gc: GC_INITIAL_THRESHOLD,
+            grow_factor: GC_GROW_FACTOR,
+            stress: false,
+        }
+    }
+
+    pub fn set_stress(&mut self, stress: bool) {
+        self.stress = stress;
+    }
+
+    pub fn set_threshold(&mut self, bytes: usize) {
+        self.next_gc = bytes;
+    }
+
+    pub fn set_grow_factor(&mut self, factor: usize) {
+        self.grow_factor = factor.max(1);
+    }
+
+    pub fn bytes_allocated(&self) -> usize {
+        self.bytes_allocated
+    }
+
+    pub fn object_count(&self) -> usize {
+        self.objects.len() - self.free.len()
+    }
+
+    pub fn should_collect(&self) -> bool {
+        self.stress || self.bytes_allocated > self.next_gc
+    }
+
+    pub fn alloc(&mut self, object: Object) -> ObjRef {
+        self.bytes_allocated += object.size();
+
+        if let Some(index) = self.free.pop() {
+            self.objects[index] = Some(object);
+            ObjRef::new(index)
+        } else {
+            self.objects.push(Some(object));
+            self.marks.push(false);
+            ObjRef::new(self.objects.len() - 1)
+        }
+    }
+
+    pub fn intern<S: Into<String>>(&mut self, string: S) -> ObjRef {
+        let string = string.into();
+        if let Some(&interned) = self.strings.get(&string) {
+            return interned;
+        }
+
+        let interned = self.alloc(Object::String(string.clone()));
+        self.strings.insert(string, interned);
+        interned
+    }
+
+    pub fn alloc_string<S: Into<String>>(&mut self, string: S) -> Value {
+        Value::Object(self.intern(string))
+    }
+
+    pub fn global_slot(&mut self, name: ObjRef) -> usize {
+        if let Some(&slot) = self.global_slots.get(&name) {
+            return slot;
+        }
+
+        self.globals.push(name);
+        self.global_slots.insert(name, self.globals.len() - 1);
+        self.globals.len() - 1
+    }
+
+    pub fn find_global(&self, name: &str) -> Option<usize> {
+        let name = self.strings.get(name)?;
+        self.global_slots.get(name).copied()
+    }
+
+    pub fn global_name(&self, slot: usize) -> Option<ObjRef> {
+        self.globals.get(slot).copied()
+    }
+
+    pub fn global_names(&self) -> &[ObjRef] {
+        &self.globals
+    }
+
+    pub fn get(&self, object: ObjRef) -> &Object {
+        self.objects[object.index()]
+            .as_ref()
+            .expect("Heap Error — Use of a freed object")
+    }
+
+    pub fn get_mut(&mut self, object: ObjRef) -> &mut Object {
+        self.objects[object.index()]
+            .as_mut()
+            .expect("Heap Error — Use of a freed object")
+    }
+
+    pub fn is_string(&self, object: ObjRef) -> bool {
+        matches!(self.get(object), Object::String(_))
+    }
+
+    pub fn is_instance(&self, object: ObjRef) -> bool {
+        matches!(self.get(object), Object::Instance(_))
+    }
+
+    pub fn string(&self, object: ObjRef) -> &str {
+        match self.get(object) {
+            Object::String(string) => string,
+            other => panic!("Heap Error — Expected a string, found {:?}", other),
+        }
+    }
+
+    pub fn function(&self, object: ObjRef) -> &Function {
+        match self.get(object) {
+            Object::Function(function) => function,
+            other => panic!("Heap Error — Expected a function, found {:?}", other),
+        }
+    }
+
+    pub fn function_mut(&mut self, object: ObjRef) -> &mut Function {
+        match self.get_mut(object) {
+            Object::Function(function) => function,
+            other => panic!("Heap Error — Expected a function, found {:?}", other),
+        }
+    }
+
+    pub fn closure(&self, object: ObjRef) -> &Closure {
+        match self.get(object) {
+            Object::Closure(closure) => closure,
+            other => panic!("Heap Error — Expected a closure, found {:?}", other),
+        }
+    }
+
+    pub fn upvalue(&self, object: ObjRef) -> &Upvalue {
+        match self.get(object) {
+            Object::Upvalue(upvalue) => upvalue,
+            other => panic!("Heap Error — Expected an upvalue, found {:?}", other),
+        }
+    }
+
+    pub fn upvalue_mut(&mut self, object: ObjRef) -> &mut Upvalue {
+        match self.get_mut(object) {
+            Object::Upvalue(upvalue) => upvalue,
+            other => panic!("Heap Error — Expected an upvalue, found {:?}", other),
+        }
+    }
+
+    pub fn class(&self, object: ObjRef) -> &Class {
+        match self.get(object) {
+            Object::Class(class) => class,
+            other => panic!("Heap Error — Expected a class, found {:?}", other),
+        }
+    }
+
+    pub fn class_mut(&mut self, object: ObjRef) -> &mut Class {
+        match self.get_mut(object) {
+            Object::Class(class) => class,
+            other => panic!("Heap Error — Expected a class, found {:?}", other),
+        }
+    }
+
+    pub fn instance(&self, object: ObjRef) -> &Instance {
+        match self.get(object) {
+            Object::Instance(instance) => instance,
+            other => panic!("Heap Error — Expected an instance, found {:?}", other),
+        }
+    }
+
+    pub fn instance_mut(&mut self, object: ObjRef) -> &mut Instance {
+        match self.get_mut(object) {
+            Object::Instance(instance) => instance,
+            other => panic!("Heap Error — Expected an instance, found {:?}", other),
+        }
+    }
+
+    pub fn set_field(&mut self, instance: ObjRef, name: ObjRef, value: Value) {
+        let size = self.get(instance).size();
+        self.instance_mut(instance).set_field(name, value);
+        self.grow(instance, size);
+    }
+
+    pub fn define_method(&mut self, class: ObjRef, name: ObjRef, method: ObjRef) {
+        let size = self.get(class).size();
+        self.class_mut(class).define_method(name, method);
+        self.grow(class, size);
+    }
+
+    // Objects can outgrow the size they were allocated with
+    fn grow(&mut self, object: ObjRef, size: usize) {
+        self.bytes_allocated += self.get(object).size() - size;
+    }
+
+    /// Keeps `value` alive for as long as the returned handle is
+    pub fn root(&self, value: Value) -> Handle {
+        Handle::new(value, &self.roots)
+    }
+
+    pub fn display(&self, value: Value) -> ValueDisplay<'_> {
+        ValueDisplay { heap: self, value }
+    }
+
+    pub fn mark_value(&mut self, value: Value) {
+        if let Value::Object(object) = value {
+            self.mark_object(object);
+        }
+    }
+
+    pub fn mark_object(&mut self, object: ObjRef) {
+        let marked = &mut self.marks[object.index()];
+        if !*marked {
+            *marked = true;
+            self.gray.push(object);
+        }
+    }
+
+    pub fn collect(&mut self) {
+        for index in 0..self.globals.len() {
+            self.mark_object(self.globals[index]);
+        }
+
+        let roots: Vec<ObjRef> = self.roots.borrow().keys().copied().collect();
+        for root in roots {
+            self.mark_object(root);
+        }
+
+        while let Some(object) = self.gray.pop() {
+            self.blacken(object);
+        }
+
+        self.sweep();
+        self.next_gc = self.bytes_allocated * self.grow_factor;
+    }
+
+    fn blacken(&mut self, object: ObjRef) {
+        let Heap {
+            objects,
+            marks,
+            gray,
+            ..
+        } = self;
+
+        let mut mark = |value: Value| {
+            if let Value::Object(object) = value {
+                if !marks[object.index()] {
+                    marks[object.index()] = true;
+                    gray.push(object);
+                }
+            }
+        };
+
+        let object = objects[object.index()]
+            .as_ref()
+            .expect("Heap Error — Marked a freed object");
+
+        match object {
+            Object::String(_) | Object::Native(_) => (),
+
+            Object::Function(function) => {
+                for constant in function.chunk().constants() {
+                    mark(*constant);
+                }
+            }
+
+            Object::Closure(closure) => {
+                mark(Value::Object(closure.function()));
+                for upvalue in closure.upvalues() {
+                    mark(Value::Object(*upvalue));
+                }
+            }
+
+            Object::Upvalue(upvalue) => {
+                if let Upvalue::Closed(value) = upvalue {
+                    mark(*value);
+                }
+            }
+
+            Object::Class(class) => {
+                for (name, method) in class.methods() {
+                    mark(Value::Object(*name));
+                    mark(Value::Object(*method));
+                }
+            }
+
+            Object::Instance(instance) => {
+                mark(Value::Object(instance.class()));
+                for (name, value) in instance.fields() {
+                    mark(Value::Object(*name));
+                    mark(*value);
+                }
+            }
+
+            Object::BoundMethod(bound) => {
+                mark(bound.receiver());
+                mark(Value::Object(bound.method()));
+            }
+        }
+    }
+
+    fn sweep(&mut self) {
+        self.bytes_allocated = 0;
+
+        for index in 0..self.objects.len() {
+            let Some(object) = &self.objects[index] else {
+                continue;
+            };
+
+            if self.marks[index] {
+                self.marks[index] = false;
+                self.bytes_allocated += object.size();
+            } else {
+                if let Some(Object::String(string)) = self.objects[index].take() {
+                    self.strings.remove(&string);
+                }
+                self.free.push(index);
+            }
+        }
+    }
+
+    fn fmt_object(&self, object: ObjRef, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.get(object) {
+            Object::String(string) => write!(f, "{}", string),
+            Object::Function(function) => match function.name() {
+                Some(name) => write!(f, "<fn {}>", name),
+                None => write!(f, "<script>"),
+            },
+            Object::Native(_) => write!(f, "<native fn>"),
+            Object::Closure(closure) => self.fmt_object(closure.function(), f),
+            Object::Upvalue(_) => write!(f, "upvalue"),
+            Object::Class(class) => write!(f, "{}", class.name()),
+            Object::Instance(instance) => {
+                write!(f, "{} instance", self.class(instance.class()).name())
+            }
+            Object::BoundMethod(bound) => self.fmt_object(bound.method(), f),
+        }
+    }
+}
+
+impl Default for Heap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct ValueDisplay<'h> {
+    heap: &'h Heap,
+    value: Value,
+}
+
+impl Display for ValueDisplay<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.value {
+            Value::Number(number) => write!(f, "{}", number),
+            Value::Boolean(boolean) => write!(f, "{}", boolean),
+            Value::Nil => write!(f, "nil"),
+            Value::Object(object) => self.heap.fmt_object(object, f),
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use shared::value::{self, Graph};
+
+use crate::repr::{
+    class::Instance,
+    native::NativeCtx,
+    object::{ObjRef, Object},
+    value::Value,
+};
+
+use super::heap::Heap;
+
+pub fn clock(_ctx: &mut NativeCtx, _arguments: &[Value]) -> Result<Value, String> {
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Failed to get the system time")
+        .as_millis() as f64
+        / 1000.0;
+
+    Ok(Value::Number(time))
+}
+
+pub fn freeze(ctx: &mut NativeCtx, arguments: &[Value]) -> Result<Value, String> {
+    let heap = ctx.heap();
+    // Freezing a bound method freezes the instance it is bound to
+    let target = match arguments[0].as_object().map(|object| heap.get(object)) {
+        Some(Object::BoundMethod(bound)) => bound.receiver(),
+        _ => arguments[0],
+    };
+
+    value::freeze(heap, &target);
+    Ok(arguments[0])
+}
+
+pub fn deep_copy(ctx: &mut NativeCtx, arguments: &[Value]) -> Result<Value, String> {
+    Ok(value::deep_copy(ctx.heap(), &arguments[0]))
+}
+
+pub fn deep_equal(ctx: &mut NativeCtx, arguments: &[Value]) -> Result<Value, String> {
+    let equal = value::deep_equal(ctx.heap(), &arguments[0], &arguments[1]);
+    Ok(Value::Boolean(equal))
+}
+
+// Instances are the only aggregates. Fields are keyed by their interned names
+impl Graph for Heap {
+    type Value = Value;
+    type Node = ObjRef;
+    type Key = ObjRef;
+
+    fn equal(&self, a: &Value, b: &Value) -> bool {
+        value::equal(a.operand(), b.operand())
+    }
+
+    fn node(&self, value: &Value) -> Option<ObjRef> {
+        value.as_object().filter(|&object| self.is_instance(object))
+    }
+
+    fn value(&self, node: ObjRef) -> Value {
+        Value::Object(node)
+    }
+
+    fn same_shape(&self, a: ObjRef, b: ObjRef) -> bool {
+        let (a, b) = (self.instance(a), self.instance(b));
+        a.class() == b.class() && a.fields().len() == b.fields().len()
+    }
+
+    fn fields(&self, node: ObjRef) -> Vec<(ObjRef, Value)> {
+        let fields = self.instance(node).fields();
+        fields.iter().map(|(name, value)| (*name, *value)).collect()
+    }
+
+    fn field(&self, node: ObjRef, key: ObjRef) -> Option<Value> {
+        self.instance(node).field(key)
+    }
+
+    fn freeze(&mut self, node: ObjRef) -> bool {
+        let instance = self.instance_mut(node);
+        let thawed = !instance.is_frozen();
+        instance.freeze();
+        thawed
+    }
+
+    fn alloc_like(&mut self, node: ObjRef) -> ObjRef {
+        let class = self.instance(node).class();
+        self.alloc(Object::Instance(Instance::new(class)))
+    }
+
+    fn set_field(&mut self, node: ObjRef, key: ObjRef, value: Value) {
+        Heap::set_field(self, node, key, value);
+    }
+}
@@ -0,0 +1,306 @@
+use crate::repr::{
+    chunk::{Chunk, Location},
+    object::{ObjRef, Object},
+    opcode::Instruction,
+};
+
+use super::heap::Heap;
+
+pub fn optimize(script: ObjRef, heap: &mut Heap) {
+    // Each function is rewritten on its own, so nesting needs no recursion
+    let mut pending = vec![script];
+
+    while let Some(function) = pending.pop() {
+        pending.extend(
+            heap.function(function)
+                .chunk()
+                .constants()
+                .iter()
+                .filter_map(|constant| constant.as_object())
+                .filter(|&object| matches!(heap.get(object), Object::Function(_))),
+        );
+
+        optimize_function(function, heap);
+    }
+}
+
+fn optimize_function(function: ObjRef, heap: &mut Heap) {
+    let mut ops = decode(heap.function(function).chunk(), heap);
+
+    loop {
+        let changed = fuse_comparisons(&mut ops)
+            | drop_unused_values(&mut ops)
+            | thread_jumps(&mut ops)
+            | drop_empty_jumps(&mut ops);
+
+        if !changed {
+            break;
+        }
+    }
+    merge_pops(&mut ops);
+
+    let chunk = heap.function_mut(function).chunk_mut();
+    if let Some(optimized) = encode(&ops, chunk) {
+        *chunk = optimized;
+    }
+}
+
+pub(super) struct Op {
+    pub(super) instruction: Instruction,
+    pub(super) operands: Vec<u8>,
+    target: usize,
+    location: Location,
+}
+
+pub(super) fn decode(chunk: &Chunk, heap: &Heap) -> Vec<Op> {
+    let code = chunk.code();
+    let mut ops = vec![];
+    let mut indices = vec![0; code.len() + 1];
+    let mut offset = 0;
+
+    while offset < code.len() {
+        let instruction =
+            Instruction::try_from(code[offset]).expect("Optimizer Error — Unknown opcode");
+        let mut len = instruction.operand_len();
+
+        if let Instruction::Closure | Instruction::ClosureLong = instruction {
+            let index = operand(&code[offset + 1..offset + 1 + len]);
+            let function = chunk.constants()[index]
+                .as_object()
+                .expect("Optimizer Error — Closure over a non-function");
+            len += heap.function(function).upvalue_count() * 2;
+        }
+
+        let operands = code[offset + 1..offset + 1 + len].to_vec();
+        let next = offset + 1 + len;
+        let target = match instruction {
+            Instruction::Jump | Instruction::JumpIfFalse => next + operand(&operands),
+            Instruction::Loop => next - operand(&operands),
+            _ => 0,
+        };
+
+        indices[offset] = ops.len();
+        ops.push(Op {
+            instruction,
+            operands,
+            target,
+            location: chunk.location(offset),
+        });
+        offset = next;
+    }
+    indices[code.len()] = ops.len();
+
+    // Jump targets are byte offsets until here, and op indices from now on
+    for op in ops.iter_mut().filter(|op| is_jump(op.instruction)) {
+        op.target = indices[op.target];
+    }
+
+    ops
+}
+
+pub(super) fn encode(ops: &[Op], chunk: &Chunk) -> Option<Chunk> {
+    let mut offsets = Vec::with_capacity(ops.len() + 1);
+    let mut offset = 0;
+    for op in ops {
+        offsets.push(offset);
+        offset += 1 + op.operands.len();
+    }
+    offsets.push(offset);
+
+    let mut encoded = Chunk::from_parts(vec![], vec![], chunk.constants().to_vec());
+
+    for (index, op) in ops.iter().enumerate() {
+        if !is_jump(op.instruction) {
+            encoded.write(op.instruction, op.location);
+            for &byte in &op.operands {
+                encoded.write_byte(byte);
+            }
+            continue;
+        }
+
+        let next = offsets[index] + 3;
+        let target = offsets[op.target];
+        let (instruction, jump) = match op.instruction {
+            Instruction::JumpIfFalse => (Instruction::JumpIfFalse, target.checked_sub(next)?),
+            _ if target >= next => (Instruction::Jump, target - next),
+            _ => (Instruction::Loop, next - target),
+        };
+
+        encoded.write(instruction, op.location);
+        for byte in u16::try_from(jump).ok()?.to_be_bytes() {
+            encoded.write_byte(byte);
+        }
+    }
+
+    Some(encoded)
+}
+
+fn fuse_comparisons(ops: &mut Vec<Op>) -> bool {
+    use Instruction::*;
+
+    let targets = targets(ops);
+    let mut removed = vec![false; ops.len()];
+
+    for index in 1..ops.len() {
+        if removed[index - 1] || targets[index] || !matches!(ops[index].instruction, Not) {
+            continue;
+        }
+
+        let fused = match ops[index - 1].instruction {
+            Equal => NotEqual,
+            Less => GreaterEqual,
+            Greater => LessEqual,
+            _ => continue,
+        };
+
+        ops[index - 1].instruction = fused;
+        removed[index] = true;
+    }
+
+    compact(ops, &removed)
+}
+
+fn drop_unused_values(ops: &mut Vec<Op>) -> bool {
+    use Instruction::*;
+
+    let targets = targets(ops);
+    let mut removed = vec![false; ops.len()];
+
+    for index in 1..ops.len() {
+        let pure = matches!(
+            ops[index - 1].instruction,
+            Constant | ConstantLong | Nil | True | False | GetLocal | GetUpvalue
+        );
+
+        if pure && !removed[index - 1] && !targets[index] && matches!(ops[index].instruction, Pop) {
+            removed[index - 1] = true;
+            removed[index] = true;
+        }
+    }
+
+    compact(ops, &removed)
+}
+
+fn thread_jumps(ops: &mut [Op]) -> bool {
+    let mut changed = false;
+
+    for index in 0..ops.len() {
+        if !is_jump(ops[index].instruction) {
+            continue;
+        }
+
+        let mut target = ops[index].target;
+        for _ in 0..ops.len() {
+            match ops.get(target) {
+                Some(op) if unconditional(op.instruction) && op.target != target => {
+                    target = op.target
+                }
+                _ => break,
+            }
+        }
+
+        let forward = target > index;
+        if target != ops[index].target && (forward || unconditional(ops[index].instruction)) {
+            ops[index].target = target;
+            changed = true;
+        }
+    }
+
+    changed
+}
+
+fn drop_empty_jumps(ops: &mut Vec<Op>) -> bool {
+    let removed: Vec<bool> = ops
+        .iter()
+        .enumerate()
+        .map(|(index, op)| unconditional(op.instruction) && op.target == index + 1)
+        .collect();
+
+    compact(ops, &removed)
+}
+
+fn merge_pops(ops: &mut Vec<Op>) {
+    let targets = targets(ops);
+    let mut removed = vec![false; ops.len()];
+
+    let mut index = 0;
+    while index < ops.len() {
+        if !matches!(ops[index].instruction, Instruction::Pop) {
+            index += 1;
+            continue;
+        }
+
+        let mut count = 1;
+        while count < u8::MAX as usize
+            && index + count < ops.len()
+            && matches!(ops[index + count].instruction, Instruction::Pop)
+            && !targets[index + count]
+        {
+            removed[index + count] = true;
+            count += 1;
+        }
+
+        if count > 1 {
+            ops[index].instruction = Instruction::PopN;
+            ops[index].operands = vec![count as u8];
+        }
+        index += count;
+    }
+
+    compact(ops, &removed);
+}
+
+fn compact(ops: &mut Vec<Op>, removed: &[bool]) -> bool {
+    if !removed.contains(&true) {
+        return false;
+    }
+
+    // A removed op hands its incoming jumps to the next op that survives
+    let mut indices = Vec::with_capacity(ops.len() + 1);
+    let mut kept = 0;
+    for &removed in removed {
+        indices.push(kept);
+        if !removed {
+            kept += 1;
+        }
+    }
+    indices.push(kept);
+
+    let mut index = 0;
+    ops.retain_mut(|op| {
+        let keep = !removed[index];
+        index += 1;
+
+        if is_jump(op.instruction) {
+            op.target = indices[op.target];
+        }
+        keep
+    });
+
+    true
+}
+
+fn targets(ops: &[Op]) -> Vec<bool> {
+    let mut targets = vec![false; ops.len() + 1];
+    for op in ops.iter().filter(|op| is_jump(op.instruction)) {
+        targets[op.target] = true;
+    }
+    targets
+}
+
+pub(super) fn operand(bytes: &[u8]) -> usize {
+    bytes
+        .iter()
+        .fold(0, |operand, &byte| (operand << 8) | byte as usize)
+}
+
+fn is_jump(instruction: Instruction) -> bool {
+    matches!(
+        instruction,
+        Instruction::Jump | Instruction::JumpIfFalse | Instruction::Loop
+    )
+}
+
+fn unconditional(instruction: Instruction) -> bool {
+    matches!(instruction, Instruction::Jump | Instruction::Loop)
+}
@@ -1,9 +1,12 @@
-use crate::repr::token::{Token, TokenType};
+use crate::repr::token::{Span, Token, TokenType};
 
 pub struct Scanner {
     source: Vec<char>,
     current: usize,
+    offset: usize,
     line: usize,
+    column: usize,
+    start: Span,
 }
 
 impl Scanner {
@@ -12,12 +15,16 @@ impl Scanner {
         Scanner {
             source,
             current: 0,
+            offset: 0,
             line: 1,
+            column: 1,
+            start: Span::default(),
         }
     }
 
     pub fn scan(&mut self) -> Token {
         self.skip_whitespace();
+        self.start = Span::new(self.offset, self.offset, self.line, self.column);
         if self.at_end() {
             return self.finish();
         }
@@ -56,8 +63,18 @@ impl Scanner {
     }
 
     fn advance(&mut self) -> char {
+        let c = self.source[self.current];
         self.current += 1;
-        self.source[self.current - 1]
+        self.offset += c.len_utf8();
+
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+
+        c
     }
 
     fn peek(&self) -> Option<char> {
@@ -70,7 +87,7 @@ impl Scanner {
 
     fn match_next(&mut self, expected: char, a: TokenType, b: TokenType) -> TokenType {
         if self.peek() == Some(expected) && !self.at_end() {
-            self.current += 1;
+            self.advance();
             a
         } else {
             b
@@ -80,21 +97,14 @@ impl Scanner {
     fn skip_whitespace(&mut self) {
         while let Some(c) = self.peek() {
             match c {
-                '/' => {
-                    if self.peek_next() == Some('/') {
-                        while self.peek() != Some('\n') {
-                            // A comment goes until the end of the line
-                            self.advance();
-                        }
-                    } else {
-                        return;
+                '/' if self.peek_next() == Some('/') => {
+                    // A comment goes until the end of the line
+                    while !matches!(self.peek(), Some('\n') | None) {
+                        self.advance();
                     }
                 }
 
                 _ if c.is_ascii_whitespace() => {
-                    if c == '\n' {
-                        self.line += 1;
-                    }
                     self.advance();
                 }
 
@@ -110,10 +120,6 @@ impl Scanner {
                 break;
             }
 
-            if c == '\n' {
-                self.line += 1;
-            }
-
             let c = self.advance();
             lexeme.push(c);
         }
@@ -196,7 +202,13 @@ impl Scanner {
     }
 
     fn create<S: Into<String>>(&self, kind: TokenType, lexeme: S) -> Token {
-        Token::new(kind, lexeme.into(), self.line)
+        let span = Span::new(
+            self.start.start(),
+            self.offset,
+            self.start.line(),
+            self.start.column(),
+        );
+        Token::new(kind, lexeme.into(), span)
     }
 
     fn finish(&self) -> Token {
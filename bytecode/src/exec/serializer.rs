@@ -0,0 +1,415 @@
+use std::rc::Rc;
+
+use crate::repr::{
+    chunk::{Chunk, LineRun, Location},
+    error::{LoxError, LoxResult},
+    function::Function,
+    object::{ObjRef, Object},
+    opcode::Instruction,
+    value::Value,
+};
+
+use super::{heap::Heap, optimizer};
+
+const MAGIC: &[u8; 4] = b"LOXC";
+const VERSION: u16 = 5;
+
+const TAG_NIL: u8 = 0;
+const TAG_FALSE: u8 = 1;
+const TAG_TRUE: u8 = 2;
+const TAG_NUMBER: u8 = 3;
+const TAG_STRING: u8 = 4;
+const TAG_FUNCTION: u8 = 5;
+
+pub fn serialize(function: ObjRef, heap: &Heap, embed_source: bool) -> Vec<u8> {
+    let mut writer = Writer {
+        heap,
+        bytes: vec![],
+    };
+
+    writer.bytes.extend_from_slice(MAGIC);
+    writer.bytes.extend_from_slice(&VERSION.to_le_bytes());
+    writer.globals();
+    match heap.function(function).source() {
+        Some(source) if embed_source => {
+            writer.bytes.push(1);
+            writer.string(source);
+        }
+        _ => writer.bytes.push(0),
+    }
+    writer.function(function);
+
+    writer.bytes
+}
+
+/// Returns the script function and the heap's global slot for each global in the file
+pub fn deserialize(bytes: &[u8], heap: &mut Heap) -> LoxResult<(ObjRef, Vec<usize>)> {
+    let mut reader = Reader {
+        heap,
+        bytes,
+        offset: 0,
+        source: None,
+        depth: 0,
+    };
+
+    if reader.take(MAGIC.len())? != MAGIC {
+        return Err(LoxError::BytecodeError(String::from(
+            "Not a klox bytecode file",
+        )));
+    }
+
+    let version = u16::from_le_bytes([reader.byte()?, reader.byte()?]);
+    if version != VERSION {
+        return Err(LoxError::BytecodeError(format!(
+            "Unsupported bytecode version {} (expected {})",
+            version, VERSION
+        )));
+    }
+
+    let globals = reader.globals()?;
+    reader.source()?;
+    let function = reader.function()?;
+
+    if reader.offset != bytes.len() {
+        return Err(LoxError::BytecodeError(String::from(
+            "Trailing data after the script function",
+        )));
+    }
+
+    Ok((function, globals))
+}
+
+/// Points the global operands of verified bytecode at the slots `deserialize` bound them to
+pub fn relink(function: ObjRef, globals: &[usize], heap: &mut Heap) -> LoxResult<()> {
+    relink_function(function, globals, heap, 0)
+}
+
+fn relink_function(
+    function: ObjRef,
+    globals: &[usize],
+    heap: &mut Heap,
+    depth: usize,
+) -> LoxResult<()> {
+    if depth > crate::FUNCTION_DEPTH_MAX {
+        return Err(LoxError::BytecodeError(String::from(
+            "Functions nested too deeply to relink",
+        )));
+    }
+
+    let nested: Vec<ObjRef> = heap
+        .function(function)
+        .chunk()
+        .constants()
+        .iter()
+        .filter_map(|constant| constant.as_object())
+        .filter(|&object| matches!(heap.get(object), Object::Function(_)))
+        .collect();
+
+    for nested in nested {
+        relink_function(nested, globals, heap, depth + 1)?;
+    }
+
+    let mut ops = optimizer::decode(heap.function(function).chunk(), heap);
+    let mut changed = false;
+
+    for op in ops.iter_mut().filter(|op| is_global(op.instruction)) {
+        let slot = globals[optimizer::operand(&op.operands)];
+
+        // The file may have used the short form for a slot that is now too large for it
+        let [_, addr_a, addr_b, addr_c] = (slot as u32).to_be_bytes();
+        let (instruction, operands) = match u8::try_from(slot) {
+            Ok(byte) if !op.instruction.is_long() => (op.instruction, vec![byte]),
+            _ => {
+                let long = op.instruction.long().unwrap_or(op.instruction);
+                (long, vec![addr_a, addr_b, addr_c])
+            }
+        };
+
+        changed |= operands != op.operands;
+        op.instruction = instruction;
+        op.operands = operands;
+    }
+
+    if !changed {
+        return Ok(());
+    }
+
+    let chunk = heap.function_mut(function).chunk_mut();
+    match optimizer::encode(&ops, chunk) {
+        Some(relinked) => {
+            *chunk = relinked;
+            Ok(())
+        }
+        None => Err(LoxError::BytecodeError(String::from(
+            "Too much code to jump over after relinking globals",
+        ))),
+    }
+}
+
+fn is_global(instruction: Instruction) -> bool {
+    use Instruction::*;
+
+    matches!(
+        instruction,
+        DefineGlobal | DefineGlobalLong | SetGlobal | SetGlobalLong | GetGlobal | GetGlobalLong
+    )
+}
+
+struct Writer<'h> {
+    heap: &'h Heap,
+    bytes: Vec<u8>,
+}
+
+impl Writer<'_> {
+    fn globals(&mut self) {
+        let names = self.heap.global_names();
+
+        self.usize(names.len());
+        for &name in names {
+            self.string(self.heap.string(name));
+        }
+    }
+
+    fn function(&mut self, function: ObjRef) {
+        let function = self.heap.function(function);
+
+        match function.name() {
+            Some(name) => {
+                self.bytes.push(1);
+                self.string(name);
+            }
+            None => self.bytes.push(0),
+        }
+
+        self.usize(function.arity());
+        self.usize(function.upvalue_count());
+
+        let chunk = function.chunk();
+
+        self.usize(chunk.len());
+        self.bytes.extend_from_slice(chunk.code());
+
+        // Runs are stored as deltas from the previous one, which mostly fit in a byte each
+        self.usize(chunk.lines().len());
+        let (mut start, mut line) = (0, 0);
+        for run in chunk.lines() {
+            let location = run.location();
+            self.varint((run.start() - start) as u64);
+            self.varint(zigzag(location.line() as i64 - line as i64));
+            self.varint(location.column() as u64);
+            self.varint(location.width() as u64);
+            (start, line) = (run.start(), location.line());
+        }
+
+        self.usize(chunk.constants().len());
+        for constant in chunk.constants() {
+            self.value(*constant);
+        }
+    }
+
+    fn value(&mut self, value: Value) {
+        match value {
+            Value::Nil => self.bytes.push(TAG_NIL),
+            Value::Boolean(false) => self.bytes.push(TAG_FALSE),
+            Value::Boolean(true) => self.bytes.push(TAG_TRUE),
+
+            Value::Number(number) => {
+                self.bytes.push(TAG_NUMBER);
+                self.bytes.extend_from_slice(&number.to_le_bytes());
+            }
+
+            Value::Object(object) => match self.heap.get(object) {
+                Object::String(string) => {
+                    self.bytes.push(TAG_STRING);
+                    self.string(string);
+                }
+
+                Object::Function(_) => {
+                    self.bytes.push(TAG_FUNCTION);
+                    self.function(object);
+                }
+
+                other => panic!("Serializer Error — Unexpected constant {:?}", other),
+            },
+        }
+    }
+
+    fn string(&mut self, string: &str) {
+        self.usize(string.len());
+        self.bytes.extend_from_slice(string.as_bytes());
+    }
+
+    fn usize(&mut self, value: usize) {
+        self.bytes.extend_from_slice(&(value as u32).to_le_bytes());
+    }
+
+    fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.bytes.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        self.bytes.push(value as u8);
+    }
+}
+
+struct Reader<'a, 'h> {
+    heap: &'h mut Heap,
+    bytes: &'a [u8],
+    offset: usize,
+    source: Option<Rc<str>>,
+    depth: usize,
+}
+
+impl<'a> Reader<'a, '_> {
+    fn globals(&mut self) -> LoxResult<Vec<usize>> {
+        let count = self.usize()?;
+        let mut slots = Vec::with_capacity(count.min(self.bytes.len()));
+
+        for _ in 0..count {
+            let name = self.string()?;
+            let interned = self.heap.intern(name.as_str());
+
+            let slot = self.heap.global_slot(interned);
+            if slot >= 1 << 24 {
+                return Err(self.error(&format!("Too many globals to bind '{}'", name)));
+            }
+            slots.push(slot);
+        }
+
+        Ok(slots)
+    }
+
+    fn source(&mut self) -> LoxResult<()> {
+        self.source = match self.byte()? {
+            0 => None,
+            1 => Some(Rc::from(self.string()?)),
+            flag => return Err(self.error(&format!("Invalid source flag {}", flag))),
+        };
+
+        Ok(())
+    }
+
+    fn function(&mut self) -> LoxResult<ObjRef> {
+        if self.depth > crate::FUNCTION_DEPTH_MAX {
+            return Err(self.error("Functions nested too deeply"));
+        }
+
+        let name = match self.byte()? {
+            0 => None,
+            1 => Some(self.string()?),
+            flag => return Err(self.error(&format!("Invalid function name flag {}", flag))),
+        };
+
+        let mut function = Function::new(name);
+        function.set_arity(self.usize()?);
+        function.set_upvalue_count(self.usize()?);
+        if let Some(source) = &self.source {
+            function.set_source(Rc::clone(source));
+        }
+
+        let len = self.usize()?;
+        let code = self.take(len)?.to_vec();
+
+        let run_count = self.usize()?;
+        let mut lines = Vec::with_capacity(run_count.min(len));
+        let (mut start, mut line) = (0u64, 0i64);
+        for _ in 0..run_count {
+            start = start.saturating_add(self.varint()?);
+            line = line.saturating_add(unzigzag(self.varint()?));
+            let column = self.varint()?;
+            let width = self.varint()?;
+
+            let (Ok(start), Ok(line)) = (usize::try_from(start), usize::try_from(line)) else {
+                return Err(self.error("Invalid line table"));
+            };
+            let location = Location::new(line, column as usize, width as usize);
+            lines.push(LineRun::new(start, location));
+        }
+
+        let constant_count = self.usize()?;
+        let mut constants = vec![];
+        self.depth += 1;
+        for _ in 0..constant_count {
+            constants.push(self.value()?);
+        }
+        self.depth -= 1;
+
+        *function.chunk_mut() = Chunk::from_parts(code, lines, constants);
+        Ok(self.heap.alloc(Object::Function(function)))
+    }
+
+    fn value(&mut self) -> LoxResult<Value> {
+        let value = match self.byte()? {
+            TAG_NIL => Value::Nil,
+            TAG_FALSE => Value::Boolean(false),
+            TAG_TRUE => Value::Boolean(true),
+
+            TAG_NUMBER => {
+                let bytes = self.take(8)?;
+                Value::Number(f64::from_le_bytes(bytes.try_into().expect("8 bytes")))
+            }
+
+            TAG_STRING => {
+                let string = self.string()?;
+                self.heap.alloc_string(string)
+            }
+
+            TAG_FUNCTION => Value::Object(self.function()?),
+
+            tag => return Err(self.error(&format!("Invalid constant tag {}", tag))),
+        };
+
+        Ok(value)
+    }
+
+    fn string(&mut self) -> LoxResult<String> {
+        let len = self.usize()?;
+        let bytes = self.take(len)?;
+
+        String::from_utf8(bytes.to_vec()).map_err(|_| self.error("Invalid UTF-8 in string"))
+    }
+
+    fn usize(&mut self) -> LoxResult<usize> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes(bytes.try_into().expect("4 bytes")) as usize)
+    }
+
+    fn varint(&mut self) -> LoxResult<u64> {
+        let mut value = 0;
+
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+
+        Err(self.error("Varint too long"))
+    }
+
+    fn byte(&mut self) -> LoxResult<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn take(&mut self, len: usize) -> LoxResult<&'a [u8]> {
+        let Some(bytes) = self.bytes.get(self.offset..self.offset.saturating_add(len)) else {
+            return Err(self.error("Unexpected end of file"));
+        };
+
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    fn error(&self, message: &str) -> LoxError {
+        LoxError::BytecodeError(format!("{} at byte {}", message, self.offset))
+    }
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn unzigzag(value: u64) -> i64 {
+    (value >> 1) as i64 ^ -((value & 1) as i64)
+}
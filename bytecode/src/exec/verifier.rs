@@ -0,0 +1,355 @@
+use std::ops::Range;
+
+use crate::repr::{
+    error::{VerifyError, VerifyErrorKind},
+    function::Function,
+    object::{ObjRef, Object},
+    opcode::Instruction,
+};
+
+use super::heap::Heap;
+
+/// `globals` is how many global names the bytecode was compiled against
+pub fn verify(script: ObjRef, globals: usize, heap: &Heap) -> Result<(), VerifyError> {
+    // Nothing encloses the script, so it has nothing to capture
+    let function = heap.function(script);
+    if function.upvalue_count() != 0 {
+        let kind = VerifyErrorKind::ScriptUpvalues(function.upvalue_count());
+        return Err(VerifyError::new(function.name(), 0, kind));
+    }
+
+    verify_function(script, globals, heap, 0)
+}
+
+fn verify_function(
+    function: ObjRef,
+    globals: usize,
+    heap: &Heap,
+    depth: usize,
+) -> Result<(), VerifyError> {
+    if depth > crate::FUNCTION_DEPTH_MAX {
+        let name = heap.function(function).name();
+        return Err(VerifyError::new(name, 0, VerifyErrorKind::NestedTooDeeply));
+    }
+
+    let mut verifier = Verifier::new(heap.function(function), globals, heap);
+    verifier.verify()?;
+
+    for constant in heap.function(function).chunk().constants() {
+        if let Some(nested) = constant.as_object() {
+            if let Object::Function(_) = heap.get(nested) {
+                verify_function(nested, globals, heap, depth + 1)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+struct Decoded {
+    instruction: Instruction,
+    operand: usize,
+    captures: Range<usize>,
+    next: usize,
+}
+
+struct Verifier<'h> {
+    function: &'h Function,
+    globals: usize,
+    heap: &'h Heap,
+    boundaries: Vec<bool>,
+}
+
+impl<'h> Verifier<'h> {
+    fn new(function: &'h Function, globals: usize, heap: &'h Heap) -> Self {
+        Verifier {
+            function,
+            globals,
+            heap,
+            boundaries: vec![false; function.chunk().len()],
+        }
+    }
+
+    fn verify(&mut self) -> Result<(), VerifyError> {
+        self.verify_lines()?;
+        self.verify_instructions()?;
+        self.verify_stack()
+    }
+
+    fn verify_lines(&self) -> Result<(), VerifyError> {
+        let chunk = self.function.chunk();
+        let lines = chunk.lines();
+
+        let starts_at_zero = lines.first().map(|run| run.start()) == Some(0);
+        let ordered = lines
+            .windows(2)
+            .all(|runs| runs[0].start() < runs[1].start());
+        let in_bounds = lines.iter().all(|run| run.start() < chunk.len());
+
+        if !starts_at_zero || !ordered || !in_bounds {
+            return Err(self.error(0, VerifyErrorKind::InvalidLineTable));
+        }
+
+        Ok(())
+    }
+
+    fn verify_instructions(&mut self) -> Result<(), VerifyError> {
+        let len = self.function.chunk().len();
+
+        if len == 0 {
+            return Err(self.error(0, VerifyErrorKind::MissingReturn));
+        }
+
+        let mut jumps = vec![];
+
+        let mut offset = 0;
+        while offset < len {
+            self.boundaries[offset] = true;
+            let decoded = self.decode(offset)?;
+            self.verify_operand(offset, &decoded)?;
+
+            if let Instruction::Jump | Instruction::JumpIfFalse | Instruction::Loop =
+                decoded.instruction
+            {
+                jumps.push((offset, decoded.operand));
+            }
+
+            offset = decoded.next;
+        }
+
+        for (offset, target) in jumps {
+            if !self.boundaries.get(target).copied().unwrap_or(false) {
+                return Err(self.error(offset, VerifyErrorKind::InvalidJumpTarget(target)));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn verify_operand(&self, offset: usize, decoded: &Decoded) -> Result<(), VerifyError> {
+        use Instruction::*;
+
+        let constants = self.function.chunk().constants();
+        let constant = |index: usize| {
+            constants
+                .get(index)
+                .copied()
+                .ok_or_else(|| self.error(offset, VerifyErrorKind::ConstantOutOfRange(index)))
+        };
+
+        match decoded.instruction {
+            Constant | ConstantLong => {
+                constant(decoded.operand)?;
+            }
+
+            GetProperty | GetPropertyLong | SetProperty | SetPropertyLong | Class | ClassLong
+            | Method | MethodLong => {
+                let name = constant(decoded.operand)?;
+                if !name
+                    .as_object()
+                    .is_some_and(|name| self.heap.is_string(name))
+                {
+                    let kind = VerifyErrorKind::ExpectedString(decoded.operand);
+                    return Err(self.error(offset, kind));
+                }
+            }
+
+            DefineGlobal | DefineGlobalLong | SetGlobal | SetGlobalLong | GetGlobal
+            | GetGlobalLong
+                if decoded.operand >= self.globals =>
+            {
+                let kind = VerifyErrorKind::InvalidGlobal(decoded.operand);
+                return Err(self.error(offset, kind));
+            }
+
+            Closure | ClosureLong => {
+                let function = constant(decoded.operand)?;
+                let is_function = function
+                    .as_object()
+                    .is_some_and(|function| matches!(self.heap.get(function), Object::Function(_)));
+                if !is_function {
+                    let kind = VerifyErrorKind::ExpectedFunction(decoded.operand);
+                    return Err(self.error(offset, kind));
+                }
+
+                for (local, index) in self.captures(decoded) {
+                    if local > 1 || (local == 0 && index >= self.function.upvalue_count()) {
+                        return Err(self.error(offset, VerifyErrorKind::InvalidUpvalue(index)));
+                    }
+                }
+            }
+
+            GetUpvalue | SetUpvalue if decoded.operand >= self.function.upvalue_count() => {
+                let kind = VerifyErrorKind::InvalidUpvalue(decoded.operand);
+                return Err(self.error(offset, kind));
+            }
+
+            _ => (),
+        }
+
+        Ok(())
+    }
+
+    fn verify_stack(&self) -> Result<(), VerifyError> {
+        use Instruction::*;
+
+        let len = self.function.chunk().len();
+        let mut depths: Vec<Option<usize>> = vec![None; len];
+        let mut pending = vec![(0, self.function.arity() + 1)];
+
+        while let Some((offset, depth)) = pending.pop() {
+            match depths[offset] {
+                Some(expected) if expected == depth => continue,
+                Some(expected) => {
+                    let kind = VerifyErrorKind::StackMismatch {
+                        expected,
+                        found: depth,
+                    };
+                    return Err(self.error(offset, kind));
+                }
+                None => depths[offset] = Some(depth),
+            }
+
+            let decoded = self.decode(offset)?;
+            let (pops, pushes) = self.stack_effect(&decoded);
+
+            if pops > depth {
+                return Err(self.error(offset, VerifyErrorKind::StackUnderflow));
+            }
+            let after = depth - pops + pushes;
+
+            match decoded.instruction {
+                GetLocal | SetLocal if decoded.operand >= depth => {
+                    let kind = VerifyErrorKind::InvalidLocal(decoded.operand);
+                    return Err(self.error(offset, kind));
+                }
+
+                Closure | ClosureLong => {
+                    for (local, index) in self.captures(&decoded) {
+                        if local == 1 && index >= depth {
+                            return Err(self.error(offset, VerifyErrorKind::InvalidLocal(index)));
+                        }
+                    }
+                }
+
+                _ => (),
+            }
+
+            let mut successors = vec![];
+            match decoded.instruction {
+                Return => (),
+                Jump | Loop => successors.push(decoded.operand),
+                JumpIfFalse => successors.extend([decoded.next, decoded.operand]),
+                _ => successors.push(decoded.next),
+            }
+
+            for successor in successors {
+                if successor >= len || !self.boundaries[successor] {
+                    let kind = if successor == len {
+                        VerifyErrorKind::MissingReturn
+                    } else {
+                        VerifyErrorKind::InvalidJumpTarget(successor)
+                    };
+                    return Err(self.error(offset, kind));
+                }
+
+                pending.push((successor, after));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn stack_effect(&self, decoded: &Decoded) -> (usize, usize) {
+        use Instruction::*;
+
+        match decoded.instruction {
+            Constant | ConstantLong | Nil | True | False | GetLocal | GetGlobal | GetGlobalLong
+            | GetUpvalue | Closure | ClosureLong | Class | ClassLong => (0, 1),
+
+            Equal | NotEqual | Greater | GreaterEqual | Less | LessEqual | Add | Subtract
+            | Multiply | Divide => (2, 1),
+
+            Not | Negate | SetLocal | SetGlobal | SetGlobalLong | SetUpvalue | GetProperty
+            | GetPropertyLong | JumpIfFalse => (1, 1),
+
+            Print | Pop | DefineGlobal | DefineGlobalLong | CloseUpvalue | Return => (1, 0),
+
+            SetProperty | SetPropertyLong | Method | MethodLong => (2, 1),
+
+            Jump | Loop => (0, 0),
+
+            Call => (decoded.operand + 1, 1),
+            PopN => (decoded.operand, 0),
+        }
+    }
+
+    fn decode(&self, offset: usize) -> Result<Decoded, VerifyError> {
+        use Instruction::*;
+
+        let code = self.function.chunk().code();
+        let byte = code[offset];
+
+        let Ok(instruction) = Instruction::try_from(byte) else {
+            return Err(self.error(offset, VerifyErrorKind::UnknownOpcode(byte)));
+        };
+
+        let operand_len = instruction.operand_len();
+
+        let Some(operand_bytes) = code.get(offset + 1..offset + 1 + operand_len) else {
+            return Err(self.error(offset, VerifyErrorKind::TruncatedInstruction));
+        };
+
+        let operand = operand_bytes
+            .iter()
+            .fold(0, |operand, &byte| (operand << 8) | byte as usize);
+        let next = offset + 1 + operand_len;
+
+        let operand = match instruction {
+            Jump | JumpIfFalse => next + operand,
+            Loop => match next.checked_sub(operand) {
+                Some(target) => target,
+                None => return Err(self.error(offset, VerifyErrorKind::InvalidJumpTarget(0))),
+            },
+            _ => operand,
+        };
+
+        let mut captures = next..next;
+        if let Closure | ClosureLong = instruction {
+            let upvalue_count = self
+                .function
+                .chunk()
+                .constants()
+                .get(operand)
+                .and_then(|constant| constant.as_object())
+                .and_then(|function| match self.heap.get(function) {
+                    Object::Function(function) => Some(function.upvalue_count()),
+                    _ => None,
+                })
+                .unwrap_or(0);
+
+            captures.end += upvalue_count * 2;
+            if captures.end > code.len() {
+                return Err(self.error(offset, VerifyErrorKind::TruncatedInstruction));
+            }
+        }
+
+        Ok(Decoded {
+            instruction,
+            operand,
+            next: captures.end,
+            captures,
+        })
+    }
+
+    fn captures(&self, decoded: &Decoded) -> Vec<(u8, usize)> {
+        self.function.chunk().code()[decoded.captures.clone()]
+            .chunks(2)
+            .map(|pair| (pair[0], pair[1] as usize))
+            .collect()
+    }
+
+    fn error(&self, offset: usize, kind: VerifyErrorKind) -> VerifyError {
+        VerifyError::new(self.function.name(), offset, kind)
+    }
+}
@@ -1,220 +1,944 @@
-use std::collections::HashMap;
+use std::{
+    cmp::Ordering,
+    io::{BufRead, BufReader, Write},
+    ops::RangeInclusive,
+    time::Instant,
+};
+
+use shared::{interrupt::Limits, value::equal};
 
 use crate::repr::{
-    chunk::Chunk,
-    error::{LoxError, LoxResult},
+    class::{BoundMethod, Class, Instance},
+    closure::{Closure, Upvalue},
+    error::{Interrupt, LoxError, LoxResult, RuntimeError, TraceFrame},
+    function::Function,
+    native::{Native, NativeCtx, NativeFn},
+    object::{ObjRef, Object},
     opcode::Instruction,
-    value::Value,
+    value::{PackedValue, Value},
 };
 
-use super::compiler::Compiler;
+use super::{
+    compiler::Compiler,
+    convert::{Callee, FromValue, IntoArgs, IntoValue},
+    heap::Heap,
+    natives, optimizer, serializer, verifier,
+};
 
-const STACK_MAX: usize = crate::U8_COUNT;
-const STACK_INIT: Value = Value::Nil;
+const FRAMES_MAX: usize = 64;
+const STACK_MAX: usize = FRAMES_MAX * crate::U8_COUNT;
 
-pub struct VirtualMachine {
+struct CallFrame {
+    closure: ObjRef,
+    function: ObjRef,
     ip: usize,
-    chunk: Chunk,
-    stack: [Value; STACK_MAX],
-    stack_top: usize,
-    globals: HashMap<String, Value>,
+    slots: usize,
+}
+
+pub use shared::interrupt::InterruptHandle;
+
+pub struct VirtualMachine {
+    frames: Vec<CallFrame>,
+    stack: Vec<PackedValue>,
+    frame_limit: usize,
+    stack_limit: usize,
+    optimize: bool,
+    embed_source: bool,
+    instructions: u64,
+    limits: Limits,
+    globals: Vec<Option<Value>>,
+    open_upvalues: Vec<ObjRef>,
+    init_string: ObjRef,
+    heap: Heap,
+
+    stdout: Box<dyn Write>,
+    stderr: Box<dyn Write>,
+    stdin: Box<dyn BufRead>,
+
+    trace: Option<Box<dyn Write>>,
+    trace_lines: Option<RangeInclusive<usize>>,
 }
 
 impl VirtualMachine {
     pub fn new() -> Self {
-        VirtualMachine {
-            ip: 0,
-            chunk: Chunk::new(),
-            stack: [STACK_INIT; STACK_MAX],
-            stack_top: 0,
-            globals: HashMap::new(),
-        }
+        VirtualMachine::with_streams(
+            std::io::stdout(),
+            std::io::stderr(),
+            BufReader::new(std::io::stdin()),
+        )
+    }
+
+    pub fn with_streams<O, E, I>(stdout: O, stderr: E, stdin: I) -> Self
+    where
+        O: Write + 'static,
+        E: Write + 'static,
+        I: BufRead + 'static,
+    {
+        let mut heap = Heap::new();
+        let init_string = heap.intern("init");
+
+        let mut vm = VirtualMachine {
+            frames: Vec::with_capacity(FRAMES_MAX),
+            stack: Vec::with_capacity(crate::U8_COUNT),
+            frame_limit: FRAMES_MAX,
+            stack_limit: STACK_MAX,
+            optimize: false,
+            embed_source: false,
+            instructions: 0,
+            limits: Limits::new(),
+            globals: vec![],
+            open_upvalues: vec![],
+            init_string,
+            heap,
+
+            stdout: Box::new(stdout),
+            stderr: Box::new(stderr),
+            stdin: Box::new(stdin),
+
+            trace: None,
+            trace_lines: None,
+        };
+
+        vm.define_prelude();
+        vm
+    }
+
+    fn define_prelude(&mut self) {
+        self.define_native("clock", 0, natives::clock);
+        self.define_native("freeze", 1, natives::freeze);
+        self.define_native("deepCopy", 1, natives::deep_copy);
+        self.define_native("deepEqual", 2, natives::deep_equal);
+    }
+
+    pub fn define_native<F>(&mut self, name: &str, arity: usize, function: F)
+    where
+        F: FnMut(&mut NativeCtx, &[Value]) -> Result<Value, String> + 'static,
+    {
+        let name_ref = self.heap.intern(name);
+        self.push(Value::Object(name_ref));
+
+        let function: NativeFn = Box::new(function);
+        let native = Native::new(name.to_string(), arity, function);
+        let native = self.alloc(Object::Native(native));
+        let slot = self.heap.global_slot(name_ref);
+        self.define_global(slot, Value::Object(native));
+        self.pop();
+    }
+
+    pub fn stdout(&mut self) -> &mut dyn Write {
+        &mut self.stdout
+    }
+
+    pub fn stderr(&mut self) -> &mut dyn Write {
+        &mut self.stderr
+    }
+
+    pub fn stdin(&mut self) -> &mut dyn BufRead {
+        &mut self.stdin
+    }
+
+    pub fn set_trace<W: Write + 'static>(&mut self, out: W) {
+        self.trace = Some(Box::new(out));
+    }
+
+    pub fn set_trace_lines(&mut self, lines: Option<RangeInclusive<usize>>) {
+        self.trace_lines = lines;
+    }
+
+    pub fn clear_trace(&mut self) {
+        self.trace = None;
+    }
+
+    /// How many calls may be active at once. Only embedders can change this; the command
+    /// line always runs with the default of 64
+    pub fn set_frame_limit(&mut self, frames: usize) {
+        self.frame_limit = frames;
+    }
+
+    /// How many values the stack may hold. Like the frame limit, this is not exposed on the
+    /// command line
+    pub fn set_stack_limit(&mut self, values: usize) {
+        self.stack_limit = values;
+    }
+
+    pub fn set_optimize(&mut self, optimize: bool) {
+        self.optimize = optimize;
+    }
+
+    /// Keeps the source in compiled bytecode, so its runtime errors can be underlined
+    pub fn set_embed_source(&mut self, embed_source: bool) {
+        self.embed_source = embed_source;
+    }
+
+    pub fn set_gc_stress(&mut self, stress: bool) {
+        self.heap.set_stress(stress);
+    }
+
+    pub fn set_gc_threshold(&mut self, bytes: usize) {
+        self.heap.set_threshold(bytes);
+    }
+
+    pub fn set_gc_grow_factor(&mut self, factor: usize) {
+        self.heap.set_grow_factor(factor);
+    }
+
+    /// Limits how many more instructions the VM may execute, across every script it runs
+    pub fn set_fuel(&mut self, fuel: Option<u64>) {
+        self.limits.set_fuel(fuel);
+    }
+
+    pub fn fuel(&self) -> Option<u64> {
+        self.limits.fuel()
+    }
+
+    pub fn set_deadline(&mut self, deadline: Option<Instant>) {
+        self.limits.set_deadline(deadline);
+    }
+
+    /// Interrupting only affects a script that is already running
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.limits.handle()
+    }
+
+    /// Instructions executed since the VM was created
+    pub fn instructions(&self) -> u64 {
+        self.instructions
     }
 
     pub fn interpret(&mut self, source: &str) -> LoxResult<()> {
-        let mut parser = Compiler::new(source);
-        self.chunk = parser.compile()?;
-        self.ip = 0;
+        let function = self.compile_source(source)?;
+        self.execute(function)
+    }
+
+    pub fn interpret_bytecode(&mut self, bytes: &[u8]) -> LoxResult<()> {
+        let function = self.load(bytes)?;
+        self.execute(function)
+    }
+
+    pub fn compile(&mut self, source: &str) -> LoxResult<Vec<u8>> {
+        let function = self.compile_source(source)?;
+        Ok(serializer::serialize(
+            function,
+            &self.heap,
+            self.embed_source,
+        ))
+    }
+
+    pub fn disassemble<W: Write>(&mut self, source: &str, out: &mut W) -> LoxResult<()> {
+        let function = self.compile_source(source)?;
+        self.disassemble_function(function, out)
+            .map_err(|error| LoxError::IoError(error.to_string()))
+    }
+
+    pub fn disassemble_bytecode<W: Write>(&mut self, bytes: &[u8], out: &mut W) -> LoxResult<()> {
+        let function = self.load(bytes)?;
+
+        self.disassemble_function(function, out)
+            .map_err(|error| LoxError::IoError(error.to_string()))
+    }
+
+    fn load(&mut self, bytes: &[u8]) -> LoxResult<ObjRef> {
+        let (function, globals) = serializer::deserialize(bytes, &mut self.heap)?;
+        verifier::verify(function, globals.len(), &self.heap).map_err(LoxError::VerifyError)?;
+        serializer::relink(function, &globals, &mut self.heap)?;
 
-        self.run()
+        Ok(function)
     }
 
-    fn run(&mut self) -> LoxResult<()> {
+    fn compile_source(&mut self, source: &str) -> LoxResult<ObjRef> {
+        let mut parser = Compiler::new(source, &mut self.heap);
+        parser.set_optimize(self.optimize);
+        let function = parser.compile()?;
+
+        if self.optimize {
+            optimizer::optimize(function, &mut self.heap);
+        }
+
+        Ok(function)
+    }
+
+    fn disassemble_function<W: Write>(&self, function: ObjRef, out: &mut W) -> std::io::Result<()> {
+        let function = self.heap.function(function);
+        let chunk = function.chunk();
+
+        chunk.disassemble(function.name().unwrap_or("<script>"), &self.heap, out)?;
+
+        for constant in chunk.constants() {
+            if let Some(nested) = constant.as_object() {
+                if let Object::Function(_) = self.heap.get(nested) {
+                    writeln!(out)?;
+                    self.disassemble_function(nested, out)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn execute(&mut self, function: ObjRef) -> LoxResult<()> {
+        self.push(Value::Object(function));
+        let closure = self.alloc(Object::Closure(Closure::new(function, vec![])));
+        self.pop();
+
+        self.push(Value::Object(closure));
+
+        self.limits.start();
+        let result = self.call_closure(closure, 0).and_then(|()| self.run());
+        self.limits.stop();
+
+        result.map(|_| ())
+    }
+
+    /// Reads a global defined by a script or with [`VirtualMachine::set_global`]
+    pub fn get_global<T: FromValue>(&mut self, name: &str) -> LoxResult<T> {
+        let value = self.global(name)?;
+        self.convert(value)
+    }
+
+    fn global(&mut self, name: &str) -> LoxResult<Value> {
+        let value = self
+            .heap
+            .find_global(name)
+            .and_then(|slot| self.globals.get(slot).copied().flatten());
+
+        match value {
+            Some(value) => Ok(value),
+            None => Err(self.error(&format!("Undefined variable '{}'.", name))),
+        }
+    }
+
+    pub fn set_global<T: IntoValue>(&mut self, name: &str, value: T) {
+        let value = value.into_value(&mut self.heap);
+        let name = self.heap.intern(name);
+        let slot = self.heap.global_slot(name);
+        self.define_global(slot, value);
+    }
+
+    /// Calls a function, class or bound method and converts what it returns.
+    /// Objects come back as a [`Handle`](super::convert::Handle), which keeps them alive
+    pub fn call<'a, C, A, R>(&mut self, callee: C, arguments: A) -> LoxResult<R>
+    where
+        C: Into<Callee<'a>>,
+        A: IntoArgs,
+        R: FromValue,
+    {
+        let callee = match callee.into() {
+            Callee::Global(name) => self.global(name)?,
+            Callee::Handle(handle) => handle.value(),
+        };
+
+        let arguments = arguments.into_args(&mut self.heap);
+        let argc = arguments.len();
+
+        self.push(callee);
+        for argument in arguments {
+            self.push(argument);
+        }
+
+        self.limits.start();
+        let result = self.call_value(callee, argc).and_then(|()| {
+            // Natives and classes without an initializer have already left their result
+            if self.frames.is_empty() {
+                Ok(self.pop())
+            } else {
+                self.run()
+            }
+        });
+        self.limits.stop();
+
+        self.convert(result?)
+    }
+
+    fn convert<T: FromValue>(&mut self, value: Value) -> LoxResult<T> {
+        match T::from_value(value, &self.heap) {
+            Some(converted) => Ok(converted),
+            None => {
+                let message = format!(
+                    "Expected {} but got {}.",
+                    T::EXPECTED,
+                    self.heap.display(value)
+                );
+                Err(self.error(&message))
+            }
+        }
+    }
+
+    fn run(&mut self) -> LoxResult<Value> {
         loop {
             let byte = self.read_byte();
+            self.instructions += 1;
 
-            #[cfg(debug_assertions)]
-            self.debug(byte);
+            if let Err(interrupt) = self.limits.tick() {
+                return Err(self.interrupt(interrupt));
+            }
 
-            let maybe_instruction: LoxResult<Instruction> = byte.try_into();
+            if self.trace.is_some() {
+                self.trace(byte)
+                    .map_err(|error| LoxError::IoError(error.to_string()))?;
+            }
 
-            if let Ok(instruction) = maybe_instruction {
-                macro_rules! binary {
-                    ($kind:ident, $op:tt) => {{
-                        if let (Value::Number(a), Value::Number(b)) = self.peek_pair() {
-                            self.pop_pair();
-                            self.push(Value::$kind(a $op b));
-                        } else {
-                            self.error("Operands must be numbers.");
-                            return Err(LoxError::RuntimeError)
-                        }
-                    }};
+            let instruction: Instruction = byte.try_into()?;
+
+            if self.stack.len() >= self.stack_limit {
+                return Err(self.error("Stack overflow."));
+            }
+
+            macro_rules! binary {
+                ($kind:ident, $op:tt) => {{
+                    if let (Value::Number(a), Value::Number(b)) = self.peek_pair() {
+                        self.pop_pair();
+                        self.push(Value::$kind(a $op b));
+                    } else {
+                        return Err(self.error("Operands must be numbers."));
+                    }
+                }};
+            }
+
+            use Instruction::*;
+            match instruction {
+                Constant | ConstantLong => {
+                    let constant = self.read_constant(instruction);
+                    self.push(constant);
+                }
+
+                Nil => self.push(Value::Nil),
+                True => self.push(Value::Boolean(true)),
+                False => self.push(Value::Boolean(false)),
+
+                Greater => binary!(Boolean, >),
+                Less => binary!(Boolean, <),
+
+                GreaterEqual | LessEqual => {
+                    let (Value::Number(a), Value::Number(b)) = self.peek_pair() else {
+                        return Err(self.error("Operands must be numbers."));
+                    };
+
+                    // These replace `Less; Not` and `Greater; Not`, so NaN compares the same way
+                    let excluded = match instruction {
+                        GreaterEqual => Ordering::Less,
+                        _ => Ordering::Greater,
+                    };
+                    self.pop_pair();
+                    self.push(Value::Boolean(a.partial_cmp(&b) != Some(excluded)));
                 }
 
-                use Instruction::*;
-                match instruction {
-                    Constant => {
-                        let constant = self.read_constant();
-                        self.push(constant);
+                Equal => {
+                    let (a, b) = self.pop_pair();
+                    self.push(Value::Boolean(equal(a.operand(), b.operand())))
+                }
+
+                NotEqual => {
+                    let (a, b) = self.pop_pair();
+                    self.push(Value::Boolean(!equal(a.operand(), b.operand())))
+                }
+
+                Add => match self.peek_pair() {
+                    (Value::Object(a), Value::Object(b))
+                        if self.heap.is_string(a) && self.heap.is_string(b) =>
+                    {
+                        let string = format!("{}{}", self.heap.string(a), self.heap.string(b));
+                        let string = self.intern(string);
+                        self.pop_pair();
+                        self.push(Value::Object(string));
                     }
 
-                    Nil => self.push(Value::Nil),
-                    True => self.push(Value::Boolean(true)),
-                    False => self.push(Value::Boolean(false)),
+                    (Value::Number(a), Value::Number(b)) => {
+                        self.pop_pair();
+                        self.push(Value::Number(a + b));
+                    }
 
-                    Greater => binary!(Boolean, >),
-                    Less => binary!(Boolean, <),
+                    _ => {
+                        return Err(self.error("Operands must be two numbers or two strings."));
+                    }
+                },
+
+                Subtract => binary!(Number, -),
+                Multiply => binary!(Number, *),
+                Divide => binary!(Number, /),
+
+                Not => {
+                    let a = self.pop().truthy();
+                    self.push(Value::Boolean(!a));
+                }
 
-                    Equal => {
-                        let (a, b) = self.pop_pair();
-                        self.push(Value::Boolean(a == b))
+                Negate => {
+                    if let Value::Number(a) = self.peek(0) {
+                        self.pop();
+                        self.push(Value::Number(-a));
+                    } else {
+                        return Err(self.error("Operand must be a number"));
                     }
+                }
+
+                Print => {
+                    let value = self.pop();
+                    writeln!(self.stdout, "{}", self.heap.display(value))
+                        .map_err(|error| LoxError::IoError(error.to_string()))?;
+                }
 
-                    Add => match self.peek_pair() {
-                        (Value::String(a), Value::String(b)) => {
-                            self.pop_pair();
-                            self.push(Value::String(Box::new(*a + &*b)));
-                        }
+                Pop => {
+                    self.pop();
+                }
 
-                        (Value::Number(a), Value::Number(b)) => {
-                            self.pop_pair();
-                            self.push(Value::Number(a + b));
-                        }
+                PopN => {
+                    let count = self.read_byte() as usize;
+                    self.stack.truncate(self.stack.len() - count);
+                }
 
-                        _ => self.error("Operands must be two numbers or two strings."),
-                    },
+                DefineGlobal | DefineGlobalLong => {
+                    let slot = self.read_operand(instruction);
+                    self.define_global(slot, self.peek(0));
+                    self.pop();
+                }
 
-                    Subtract => binary!(Number, -),
-                    Multiply => binary!(Number, *),
-                    Divide => binary!(Number, /),
+                GetLocal => {
+                    let slot = self.frame().slots + self.read_byte() as usize;
+                    self.push(self.stack[slot].into());
+                }
 
-                    Not => {
-                        let a = self.pop().truthy();
-                        self.push(Value::Boolean(!a));
+                SetLocal => {
+                    let slot = self.frame().slots + self.read_byte() as usize;
+                    self.stack[slot] = self.peek(0).into();
+                }
+
+                SetGlobal | SetGlobalLong => {
+                    let slot = self.read_operand(instruction);
+                    let value = self.peek(0);
+
+                    match self.globals.get_mut(slot) {
+                        Some(Some(global)) => *global = value,
+                        _ => return Err(self.undefined_global(slot)),
                     }
+                }
 
-                    Negate => {
-                        if let Value::Number(a) = self.peek(0) {
-                            self.pop();
-                            self.push(Value::Number(-a));
-                        } else {
-                            self.error("Operand must be a number");
-                            return Err(LoxError::RuntimeError);
-                        }
+                GetGlobal | GetGlobalLong => {
+                    let slot = self.read_operand(instruction);
+
+                    if let Some(Some(value)) = self.globals.get(slot) {
+                        self.push(*value);
+                    } else {
+                        return Err(self.undefined_global(slot));
                     }
+                }
 
-                    Print => println!("{}", self.pop()),
+                GetUpvalue => {
+                    let slot = self.read_byte() as usize;
+                    let upvalue = self.upvalue(slot)?;
 
-                    Pop => {
-                        self.pop();
+                    let value = match self.heap.upvalue(upvalue) {
+                        Upvalue::Open(location) => self.stack[*location].into(),
+                        Upvalue::Closed(value) => *value,
+                    };
+                    self.push(value);
+                }
+
+                SetUpvalue => {
+                    let slot = self.read_byte() as usize;
+                    let upvalue = self.upvalue(slot)?;
+
+                    let value = self.peek(0);
+                    match self.heap.upvalue_mut(upvalue) {
+                        Upvalue::Open(location) => self.stack[*location] = value.into(),
+                        Upvalue::Closed(closed) => *closed = value,
                     }
+                }
 
-                    DefineGlobal => {
-                        let name = self.read_string();
-                        self.globals.insert(name, self.peek(0));
+                GetProperty | GetPropertyLong => {
+                    let Some(instance) = self.as_instance(self.peek(0)) else {
+                        return Err(self.error("Only instances have properties."));
+                    };
+                    let name = self.read_string(instruction)?;
+
+                    if let Some(value) = self.heap.instance(instance).field(name) {
                         self.pop();
+                        self.push(value);
+                    } else {
+                        let class = self.heap.instance(instance).class();
+                        self.bind_method(class, name)?;
                     }
+                }
 
-                    GetLocal => {
-                        let slot = self.read_byte() as usize;
-                        self.push(self.stack[slot].clone());
-                    }
+                SetProperty | SetPropertyLong => {
+                    let Some(instance) = self.as_instance(self.peek(1)) else {
+                        return Err(self.error("Only instances have fields."));
+                    };
+                    let name = self.read_string(instruction)?;
 
-                    SetLocal => {
-                        let slot = self.read_byte() as usize;
-                        self.stack[slot] = self.peek(0);
+                    if self.heap.instance(instance).is_frozen() {
+                        return Err(self.error(&format!(
+                            "Can't set property '{}' on a frozen instance.",
+                            self.heap.string(name)
+                        )));
                     }
 
-                    SetGlobal => {
-                        let name = self.read_string();
+                    let value = self.peek(0);
+                    self.heap.set_field(instance, name, value);
+                    self.pop();
+                    self.pop();
+                    self.push(value);
+                }
+
+                Jump => {
+                    let offset = self.read_short() as usize;
+                    self.frame_mut().ip += offset;
+                }
 
-                        if self.globals.insert(name.clone(), self.peek(0)).is_none() {
-                            self.globals.remove(&name);
-                            self.error(&format!("Undefined variable '{}'", name));
-                            return Err(LoxError::RuntimeError);
-                        }
+                JumpIfFalse => {
+                    let offset = self.read_short() as usize;
+                    if !self.peek(0).truthy() {
+                        self.frame_mut().ip += offset;
                     }
+                }
+
+                Loop => {
+                    let offset = self.read_short() as usize;
+                    self.frame_mut().ip -= offset;
+                }
+
+                Call => {
+                    let argc = self.read_byte() as usize;
+                    self.call_value(self.peek(argc), argc)?;
+                }
 
-                    GetGlobal => {
-                        let name = self.read_string();
+                Closure | ClosureLong => {
+                    let Value::Object(function) = self.read_constant(instruction) else {
+                        panic!("Failed to grab a function from constant table")
+                    };
 
-                        if let Some(value) = self.globals.get(&name) {
-                            self.push(value.clone());
+                    let upvalue_count = self.heap.function(function).upvalue_count();
+                    let mut upvalues = Vec::with_capacity(upvalue_count);
+                    for _ in 0..upvalue_count {
+                        let local = self.read_byte() == 1;
+                        let index = self.read_byte() as usize;
+
+                        let upvalue = if local {
+                            self.capture_upvalue(self.frame().slots + index)
                         } else {
-                            self.error(&format!("Undefined variable '{}'.", name));
-                            return Err(LoxError::RuntimeError);
-                        }
+                            self.upvalue(index)?
+                        };
+                        upvalues.push(upvalue);
                     }
 
-                    Jump => {
-                        self.ip += self.read_short() as usize;
-                    }
+                    let closure = self::Closure::new(function, upvalues);
+                    let closure = self.alloc(Object::Closure(closure));
+                    self.push(Value::Object(closure));
+                }
 
-                    JumpIfFalse => {
-                        let offset = self.read_short();
-                        if !self.peek(0).truthy() {
-                            self.ip += offset as usize;
-                        }
+                CloseUpvalue => {
+                    self.close_upvalues(self.stack.len() - 1);
+                    self.pop();
+                }
+
+                Class | ClassLong => {
+                    let name = self.read_string(instruction)?;
+                    let class = self::Class::new(self.heap.string(name).to_string());
+                    let class = self.alloc(Object::Class(class));
+                    self.push(Value::Object(class));
+                }
+
+                Method | MethodLong => {
+                    let name = self.read_string(instruction)?;
+                    self.define_method(name)?;
+                }
+
+                Return => {
+                    let result = self.pop();
+                    let frame = self.frames.pop().expect("VM Call Frame underflow");
+                    self.close_upvalues(frame.slots);
+
+                    if self.frames.is_empty() {
+                        self.stack.truncate(frame.slots);
+                        return Ok(result);
                     }
 
-                    Loop => {
-                        self.ip -= self.read_short() as usize;
+                    self.stack.truncate(frame.slots);
+                    self.push(result);
+                }
+            }
+        }
+    }
+
+    fn alloc(&mut self, object: Object) -> ObjRef {
+        if self.heap.should_collect() {
+            self.collect_garbage();
+        }
+
+        self.heap.alloc(object)
+    }
+
+    fn intern(&mut self, string: String) -> ObjRef {
+        if self.heap.should_collect() {
+            self.collect_garbage();
+        }
+
+        self.heap.intern(string)
+    }
+
+    fn collect_garbage(&mut self) {
+        for slot in &self.stack {
+            self.heap.mark_value((*slot).into());
+        }
+
+        for frame in &self.frames {
+            self.heap.mark_object(frame.closure);
+        }
+
+        for upvalue in &self.open_upvalues {
+            self.heap.mark_object(*upvalue);
+        }
+
+        for value in self.globals.iter().flatten() {
+            self.heap.mark_value(*value);
+        }
+
+        self.heap.mark_object(self.init_string);
+
+        self.heap.collect();
+    }
+
+    fn define_global(&mut self, slot: usize, value: Value) {
+        if slot >= self.globals.len() {
+            self.globals.resize(slot + 1, None);
+        }
+
+        self.globals[slot] = Some(value);
+    }
+
+    fn undefined_global(&mut self, slot: usize) -> LoxError {
+        let name = self
+            .heap
+            .global_name(slot)
+            .map_or("?", |name| self.heap.string(name))
+            .to_string();
+
+        self.error(&format!("Undefined variable '{}'.", name))
+    }
+
+    fn as_instance(&self, value: Value) -> Option<ObjRef> {
+        value
+            .as_object()
+            .filter(|&object| self.heap.is_instance(object))
+    }
+
+    fn call_value(&mut self, callee: Value, argc: usize) -> LoxResult<()> {
+        let Value::Object(callee) = callee else {
+            return Err(self.error("Can only call functions and classes."));
+        };
+
+        match self.heap.get(callee) {
+            Object::Closure(_) => self.call_closure(callee, argc),
+
+            Object::Class(class) => {
+                let initializer = class.method(self.init_string);
+
+                let instance = self.alloc(Object::Instance(Instance::new(callee)));
+                let slot = self.stack.len() - argc - 1;
+                self.stack[slot] = Value::Object(instance).into();
+
+                if let Some(initializer) = initializer {
+                    self.call_closure(initializer, argc)
+                } else if argc != 0 {
+                    Err(self.error(&format!("Expected 0 arguments but got {}.", argc)))
+                } else {
+                    Ok(())
+                }
+            }
+
+            Object::Native(native) => {
+                let arity = native.arity();
+                let function = native.function();
+
+                if argc != arity {
+                    return Err(
+                        self.error(&format!("Expected {} arguments but got {}.", arity, argc))
+                    );
+                }
+
+                let arguments: Vec<Value> = self.stack[self.stack.len() - argc..]
+                    .iter()
+                    .map(|&slot| slot.into())
+                    .collect();
+                let result =
+                    (function.borrow_mut())(&mut NativeCtx::new(&mut self.heap), &arguments);
+                match result {
+                    Ok(result) => {
+                        self.stack.truncate(self.stack.len() - argc - 1);
+                        self.push(result);
+                        Ok(())
                     }
 
-                    Return => return Ok(()),
+                    Err(message) => Err(self.error(&message)),
                 }
             }
+
+            Object::BoundMethod(bound) => {
+                let method = bound.method();
+                let slot = self.stack.len() - argc - 1;
+                self.stack[slot] = bound.receiver().into();
+                self.call_closure(method, argc)
+            }
+
+            _ => Err(self.error("Can only call functions and classes.")),
+        }
+    }
+
+    fn call_closure(&mut self, closure: ObjRef, argc: usize) -> LoxResult<()> {
+        let function = self.heap.closure(closure).function();
+        let arity = self.heap.function(function).arity();
+
+        if argc != arity {
+            return Err(self.error(&format!("Expected {} arguments but got {}.", arity, argc)));
+        }
+
+        if self.frames.len() >= self.frame_limit {
+            return Err(self.error("Stack overflow."));
+        }
+
+        self.frames.push(CallFrame {
+            closure,
+            function,
+            ip: 0,
+            slots: self.stack.len() - argc - 1,
+        });
+
+        Ok(())
+    }
+
+    fn bind_method(&mut self, class: ObjRef, name: ObjRef) -> LoxResult<()> {
+        let Some(method) = self.heap.class(class).method(name) else {
+            return Err(self.error(&format!("Undefined property '{}'.", self.heap.string(name))));
+        };
+
+        let bound = BoundMethod::new(self.peek(0), method);
+        let bound = self.alloc(Object::BoundMethod(bound));
+        self.pop();
+        self.push(Value::Object(bound));
+        Ok(())
+    }
+
+    fn define_method(&mut self, name: ObjRef) -> LoxResult<()> {
+        let (Value::Object(class), Value::Object(method)) = self.peek_pair() else {
+            return Err(self.bytecode_error("Method defined outside of a class body"));
+        };
+
+        let is_class = matches!(self.heap.get(class), Object::Class(_));
+        let is_closure = matches!(self.heap.get(method), Object::Closure(_));
+        if !is_class || !is_closure {
+            return Err(self.bytecode_error("Method defined outside of a class body"));
+        }
+
+        self.heap.define_method(class, name, method);
+        self.pop();
+        Ok(())
+    }
+
+    fn upvalue(&mut self, index: usize) -> LoxResult<ObjRef> {
+        match self.heap.closure(self.frame().closure).upvalue(index) {
+            Some(upvalue) => Ok(upvalue),
+            None => Err(self.bytecode_error(&format!("Upvalue {} out of range", index))),
+        }
+    }
+
+    fn capture_upvalue(&mut self, location: usize) -> ObjRef {
+        let heap = &self.heap;
+        let existing = self.open_upvalues.iter().find(
+            |&&upvalue| matches!(heap.upvalue(upvalue), Upvalue::Open(open) if *open == location),
+        );
+
+        if let Some(upvalue) = existing {
+            return *upvalue;
         }
+
+        let upvalue = self.alloc(Object::Upvalue(Upvalue::Open(location)));
+        self.open_upvalues.push(upvalue);
+        upvalue
+    }
+
+    fn close_upvalues(&mut self, last: usize) {
+        let Self {
+            heap,
+            stack,
+            open_upvalues,
+            ..
+        } = self;
+
+        open_upvalues.retain(|&upvalue| {
+            let upvalue = heap.upvalue_mut(upvalue);
+            match *upvalue {
+                Upvalue::Open(location) if location >= last => {
+                    *upvalue = Upvalue::Closed(stack[location].into());
+                    false
+                }
+
+                _ => true,
+            }
+        });
+    }
+
+    fn frame(&self) -> &CallFrame {
+        self.frames.last().expect("VM Call Frame underflow")
+    }
+
+    fn frame_mut(&mut self) -> &mut CallFrame {
+        self.frames.last_mut().expect("VM Call Frame underflow")
     }
 
     fn read_byte(&mut self) -> u8 {
+        let frame = self.frames.last_mut().expect("VM Call Frame underflow");
         let byte = self
-            .chunk
-            .read(self.ip)
+            .heap
+            .function(frame.function)
+            .chunk()
+            .read(frame.ip)
             .expect("VM Instruction Pointer out of bounds");
-        self.ip += 1;
+        frame.ip += 1;
         byte
     }
 
     fn read_short(&mut self) -> u16 {
-        self.ip += 2;
-        let addr_a = self.chunk.read(self.ip - 2).expect("VM Instruction Pointer out of bounds");
-        let addr_b = self.chunk.read(self.ip - 1).expect("VM Instruction Pointer out of bounds");
+        let addr_a = self.read_byte();
+        let addr_b = self.read_byte();
         u16::from_be_bytes([addr_a, addr_b])
     }
 
-    fn read_constant(&mut self) -> Value {
-        let index = self.read_byte();
-        self.chunk
+    fn read_long(&mut self) -> usize {
+        let addr_a = self.read_byte();
+        let addr_b = self.read_byte();
+        let addr_c = self.read_byte();
+        u32::from_be_bytes([0, addr_a, addr_b, addr_c]) as usize
+    }
+
+    fn read_operand(&mut self, instruction: Instruction) -> usize {
+        if instruction.is_long() {
+            self.read_long()
+        } else {
+            self.read_byte() as usize
+        }
+    }
+
+    fn read_constant(&mut self, instruction: Instruction) -> Value {
+        let index = self.read_operand(instruction);
+
+        self.heap
+            .function(self.frame().function)
+            .chunk()
             .read_constant(index)
             .expect("VM Read Constant Out of Bounds")
     }
 
-    fn read_string(&mut self) -> String {
-        let Value::String(name) = self.read_constant() else {
-            panic!("Failed to grab a string from constant table")
-        };
-        *name
+    fn read_string(&mut self, instruction: Instruction) -> LoxResult<ObjRef> {
+        match self.read_constant(instruction) {
+            Value::Object(name) if self.heap.is_string(name) => Ok(name),
+            _ => Err(self.bytecode_error("Expected a string constant")),
+        }
     }
 
     fn push(&mut self, value: Value) {
-        self.stack[self.stack_top] = value;
-        self.stack_top += 1
+        self.stack.push(value.into());
     }
 
     fn pop(&mut self) -> Value {
-        self.stack_top -= 1;
-        self.stack[self.stack_top].clone()
+        self.stack.pop().expect("VM Stack underflow").into()
     }
 
     fn pop_pair(&mut self) -> (Value, Value) {
@@ -224,27 +948,77 @@ impl VirtualMachine {
     }
 
     fn peek(&self, distance: usize) -> Value {
-        self.stack[self.stack_top - 1 - distance].clone()
+        self.stack[self.stack.len() - 1 - distance].into()
     }
 
     fn peek_pair(&self) -> (Value, Value) {
         (self.peek(1), self.peek(0))
     }
 
-    fn error(&mut self, message: &str) {
-        eprintln!("{}", message);
+    fn reset_stack(&mut self) {
+        self.stack.clear();
+        self.frames.clear();
+        self.open_upvalues.clear();
+    }
 
-        let line = self.chunk.line(-1);
-        eprintln!("[line {}] in script", line);
-        self.stack_top = 0;
+    fn interrupt(&mut self, interrupt: Interrupt) -> LoxError {
+        self.reset_stack();
+        LoxError::InterruptError(interrupt)
     }
 
-    #[cfg(debug_assertions)]
-    fn debug(&self, byte: u8) {
-        for slot in 0..self.stack_top {
-            println!("[ {} ]", self.stack[slot]);
+    fn error(&mut self, message: &str) -> LoxError {
+        let trace = self
+            .frames
+            .iter()
+            .rev()
+            .map(|frame| {
+                let function = self.heap.function(frame.function);
+                let location = function.chunk().location(frame.ip - 1);
+                TraceFrame::new(function.name().map(str::to_string), location)
+            })
+            .collect();
+
+        let mut error = RuntimeError::new(message.to_string(), trace);
+        let innermost = self
+            .frames
+            .last()
+            .map(|frame| self.heap.function(frame.function));
+        if let Some(source) = innermost.and_then(Function::source) {
+            error = error.with_source(source);
         }
-        self.chunk.disassemble_instruction(byte, self.ip - 1);
+
+        self.reset_stack();
+        LoxError::RuntimeError(error)
+    }
+
+    fn bytecode_error(&mut self, message: &str) -> LoxError {
+        self.reset_stack();
+        LoxError::BytecodeError(message.to_string())
+    }
+
+    fn trace(&mut self, byte: u8) -> std::io::Result<()> {
+        let Some(out) = self.trace.as_mut() else {
+            return Ok(());
+        };
+
+        let frame = self.frames.last().expect("VM Call Frame underflow");
+        let chunk = self.heap.function(frame.function).chunk();
+        let offset = frame.ip - 1;
+
+        if let Some(lines) = &self.trace_lines {
+            if !lines.contains(&chunk.line(offset)) {
+                return Ok(());
+            }
+        }
+
+        write!(out, "          ")?;
+        for slot in &self.stack {
+            write!(out, "[ {} ]", self.heap.display((*slot).into()))?;
+        }
+        writeln!(out)?;
+
+        chunk.disassemble_instruction(byte, offset, &self.heap, out)?;
+        Ok(())
     }
 }
 
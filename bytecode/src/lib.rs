@@ -1,54 +1,140 @@
+mod bench;
 pub mod exec;
 pub mod repr;
 
-use std::io::Write;
+use std::{ffi::OsStr, ops::RangeInclusive, path::Path};
 
+use bench::{Outcome, Report};
 use exec::vm::VirtualMachine;
 use repr::error::{LoxError, LoxResult};
 
-pub const U8_COUNT: usize = u8::MAX as usize + 1;
-
-pub fn repl() -> LoxResult<()> {
-    prompt();
+pub use bench::Engine;
 
-    let stdin = std::io::stdin();
-    let mut stdout = std::io::stdout();
+pub const U8_COUNT: usize = u8::MAX as usize + 1;
+/// How deeply function declarations may nest inside the script
+pub const FUNCTION_DEPTH_MAX: usize = 128;
 
-    let mut vm = VirtualMachine::new();
+pub fn repl(mut vm: VirtualMachine) -> LoxResult<()> {
+    let version = env!("CARGO_PKG_VERSION");
+    writeln!(vm.stdout(), "klox v{version}").expect("Failed to write to stdout");
 
     loop {
-        print!("> ");
+        let stdout = vm.stdout();
+        write!(stdout, "> ").expect("Failed to write to stdout");
         stdout.flush().expect("Failed to flush stdout");
 
         let mut line = String::new();
-        stdin.read_line(&mut line).expect("Failed to read stdin");
+        vm.stdin()
+            .read_line(&mut line)
+            .expect("Failed to read stdin");
 
         if line.is_empty() {
             break;
         }
-        let _ = vm.interpret(&line);
+        if let Err(error) = vm.interpret(&line) {
+            writeln!(vm.stderr(), "{error}").expect("Failed to write to stderr");
+        }
     }
 
     Ok(())
 }
 
-pub fn run_file(path: &str) -> LoxResult<()> {
-    let code = match std::fs::read_to_string(path) {
-        Ok(code) => code,
-        Err(error) => match error.kind() {
-            std::io::ErrorKind::NotFound => {
-                return Err(LoxError::FileNotFoundError(path.to_string()));
+pub const BYTECODE_EXTENSION: &str = "loxc";
+
+pub fn run_file(path: &str, optimize: bool) -> LoxResult<()> {
+    run_with(new_vm(optimize), path)
+}
+
+pub fn trace_file(
+    path: &str,
+    lines: Option<RangeInclusive<usize>>,
+    optimize: bool,
+) -> LoxResult<()> {
+    let mut vm = new_vm(optimize);
+    vm.set_trace(std::io::stderr());
+    vm.set_trace_lines(lines);
+
+    run_with(vm, path)
+}
+
+fn run_with(mut vm: VirtualMachine, path: &str) -> LoxResult<()> {
+    if Path::new(path).extension() == Some(OsStr::new(BYTECODE_EXTENSION)) {
+        let bytes = read_file(path)?;
+        return vm.interpret_bytecode(&bytes);
+    }
+
+    let code = read_source(path)?;
+    vm.interpret(&code)
+}
+
+pub fn disassemble_file(path: &str, optimize: bool) -> LoxResult<()> {
+    let mut vm = new_vm(optimize);
+    let mut stdout = std::io::stdout().lock();
+
+    if Path::new(path).extension() == Some(OsStr::new(BYTECODE_EXTENSION)) {
+        let bytes = read_file(path)?;
+        return vm.disassemble_bytecode(&bytes, &mut stdout);
+    }
+
+    let code = read_source(path)?;
+    vm.disassemble(&code, &mut stdout)
+}
+
+pub fn compile_file(path: &str, output: &str, optimize: bool, embed_source: bool) -> LoxResult<()> {
+    let code = read_source(path)?;
+
+    let mut vm = new_vm(optimize);
+    vm.set_embed_source(embed_source);
+    let bytes = vm.compile(&code)?;
+
+    std::fs::write(output, bytes).map_err(|error| LoxError::IoError(error.to_string()))
+}
+
+pub fn bench_files(
+    paths: &[String],
+    engine: Engine,
+    iterations: usize,
+    optimize: bool,
+    json: bool,
+) -> LoxResult<()> {
+    let mut report = Report::new(engine, iterations, optimize);
+    for path in paths {
+        let code = read_source(path)?;
+        let outcome = if !engine.supports(&code) {
+            Outcome::Skipped
+        } else {
+            match bench::measure(&code, engine, iterations, optimize) {
+                Ok(stats) => Outcome::Measured(stats),
+                Err(error) => Outcome::Failed(error),
             }
+        };
+        report.push(path, outcome);
+    }
 
-            _ => panic!("An error occurred: {}", error),
-        },
+    let mut stdout = std::io::stdout().lock();
+    let written = if json {
+        report.write_json(&mut stdout)
+    } else {
+        report.write_text(&mut stdout)
     };
 
+    written.map_err(|error| LoxError::IoError(error.to_string()))
+}
+
+fn new_vm(optimize: bool) -> VirtualMachine {
     let mut vm = VirtualMachine::new();
-    vm.interpret(&code)
+    vm.set_optimize(optimize);
+    vm
+}
+
+fn read_source(path: &str) -> LoxResult<String> {
+    let bytes = read_file(path)?;
+    String::from_utf8(bytes).map_err(|error| LoxError::IoError(error.to_string()))
 }
 
-fn prompt() {
-    let v = env!("CARGO_PKG_VERSION");
-    println!("klox v{v}")
+fn read_file(path: &str) -> LoxResult<Vec<u8>> {
+    std::fs::read(path).map_err(|error| match error.kind() {
+        std::io::ErrorKind::NotFound => LoxError::FileNotFoundError(path.to_string()),
+        _ => LoxError::IoError(error.to_string()),
+    })
 }
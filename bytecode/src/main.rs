@@ -1,24 +1,90 @@
-use std::process::{ExitCode, Termination};
+use std::{
+    ops::RangeInclusive,
+    process::{ExitCode, Termination},
+};
+
+use shared::interrupt::interrupt_on_ctrlc;
 
 use bytecode::{
+    bench_files, compile_file, disassemble_file,
+    exec::vm::VirtualMachine,
     repl,
     repr::error::{LoxError, LoxResult},
-    run_file,
+    run_file, trace_file, Engine,
 };
 
 fn main() -> ExitCode {
-    let args: Vec<String> = std::env::args().collect();
+    let mut args: Vec<String> = std::env::args().collect();
+
+    let optimize = args.iter().any(|arg| arg == "-O");
+    args.retain(|arg| arg != "-O");
     let len = args.len();
 
     let result = match len {
-        1 => repl(),
-        2 => run_file(&args[1]),
+        1 => prompt(optimize),
+        _ if args[1] == "bench" => bench(&args[2..], optimize),
+        2 => run_file(&args[1], optimize),
+        3 if args[1] == "--disassemble" => disassemble_file(&args[2], optimize),
+        3 if args[1] == "--trace" => trace_file(&args[2], None, optimize),
+        3 if args[1].starts_with("--trace=") => match parse_lines(&args[1]["--trace=".len()..]) {
+            Some(lines) => trace_file(&args[2], Some(lines), optimize),
+            None => Err(LoxError::IncorrectArgumentsError),
+        },
+        5 if args[1] == "compile" && args[3] == "-o" => {
+            compile_file(&args[2], &args[4], optimize, false)
+        }
+        6 if args[1] == "compile" && args[2] == "--embed-source" && args[4] == "-o" => {
+            compile_file(&args[3], &args[5], optimize, true)
+        }
         _ => Err(LoxError::IncorrectArgumentsError),
     };
 
     exit(result)
 }
 
+fn prompt(optimize: bool) -> LoxResult<()> {
+    let mut vm = VirtualMachine::new();
+    vm.set_optimize(optimize);
+
+    interrupt_on_ctrlc(vm.interrupt_handle());
+    repl(vm)
+}
+
+fn bench(args: &[String], optimize: bool) -> LoxResult<()> {
+    let mut engine = Engine::Bytecode;
+    let mut iterations = 10;
+    let mut json = false;
+    let mut scripts = vec![];
+
+    for arg in args {
+        if let Some(name) = arg.strip_prefix("--engine=") {
+            engine = name.parse()?;
+        } else if let Some(count) = arg.strip_prefix("--iterations=") {
+            iterations = match count.parse() {
+                Ok(count) if count > 0 => count,
+                _ => return Err(LoxError::IncorrectArgumentsError),
+            };
+        } else if arg == "--json" {
+            json = true;
+        } else if arg.starts_with("--") {
+            return Err(LoxError::IncorrectArgumentsError);
+        } else {
+            scripts.push(arg.clone());
+        }
+    }
+
+    if scripts.is_empty() {
+        return Err(LoxError::IncorrectArgumentsError);
+    }
+
+    bench_files(&scripts, engine, iterations, optimize, json)
+}
+
+fn parse_lines(lines: &str) -> Option<RangeInclusive<usize>> {
+    let (start, end) = lines.split_once('-').unwrap_or((lines, lines));
+    Some(start.parse().ok()?..=end.parse().ok()?)
+}
+
 fn exit(result: LoxResult<()>) -> ExitCode {
     match result {
         Ok(_) => ExitCode::SUCCESS,
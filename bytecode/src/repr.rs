@@ -1,5 +1,12 @@
 pub mod chunk;
+pub mod class;
+pub mod closure;
 pub mod error;
+pub mod function;
+#[cfg(feature = "nan-boxing")]
+pub mod nanbox;
+pub mod native;
+pub mod object;
 pub mod opcode;
 pub mod precedence;
 pub mod token;
@@ -1,11 +1,103 @@
-use std::fmt::Debug;
+use std::{
+    fmt::Debug,
+    io::{self, Write},
+};
 
-use super::{opcode::Instruction, value::Value};
+use crate::exec::heap::Heap;
+
+use super::{opcode::Instruction, token::Span, value::Value};
+
+/// Where an instruction came from, as a line and the character columns it covers
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Location {
+    line: u32,
+    column: u16,
+    width: u16,
+}
+
+impl Location {
+    pub fn new(line: usize, column: usize, width: usize) -> Self {
+        Location {
+            line: u32::try_from(line).unwrap_or(u32::MAX),
+            column: u16::try_from(column).unwrap_or(u16::MAX),
+            width: u16::try_from(width).unwrap_or(u16::MAX),
+        }
+    }
+
+    pub fn line(&self) -> usize {
+        self.line as usize
+    }
+
+    pub fn column(&self) -> usize {
+        self.column as usize
+    }
+
+    pub fn width(&self) -> usize {
+        self.width as usize
+    }
+
+    /// The byte range the location covers in `source`, clamped to its line
+    pub fn span(&self, source: &str) -> Span {
+        let line_start: usize = source
+            .split_inclusive('\n')
+            .take(self.line().saturating_sub(1))
+            .map(str::len)
+            .sum();
+        let line = source[line_start..].split('\n').next().unwrap_or_default();
+
+        let offset = |column: usize| {
+            line.char_indices()
+                .nth(column)
+                .map_or(line.len(), |(offset, _)| offset)
+        };
+        let start = offset(self.column().saturating_sub(1));
+        let end = offset(self.column().saturating_sub(1) + self.width()).max(start);
+
+        Span::new(
+            line_start + start,
+            line_start + end,
+            self.line(),
+            self.column(),
+        )
+    }
+
+    pub fn underline(&self, source: &str) -> String {
+        self.span(source).underline(source)
+    }
+}
+
+/// The location of every byte from `start` up to the next run
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LineRun {
+    start: u32,
+    location: Location,
+}
+
+impl LineRun {
+    pub fn new(start: usize, location: Location) -> Self {
+        LineRun {
+            start: u32::try_from(start).unwrap_or(u32::MAX),
+            location,
+        }
+    }
+
+    pub fn start(&self) -> usize {
+        self.start as usize
+    }
+
+    pub fn location(&self) -> Location {
+        self.location
+    }
+
+    pub fn line(&self) -> usize {
+        self.location.line()
+    }
+}
 
 #[derive(Debug)]
 pub struct Chunk {
     code: Vec<u8>,
-    lines: Vec<usize>,
+    lines: Vec<LineRun>,
     constants: Vec<Value>,
 }
 
@@ -18,19 +110,44 @@ impl Chunk {
         }
     }
 
-    pub fn write(&mut self, instruction: Instruction, line: usize) {
-        self.write_byte(instruction as u8, line);
+    pub fn from_parts(code: Vec<u8>, lines: Vec<LineRun>, constants: Vec<Value>) -> Self {
+        Chunk {
+            code,
+            lines,
+            constants,
+        }
+    }
+
+    pub fn write(&mut self, instruction: Instruction, location: Location) {
+        // Only instructions that can fail need their own columns, the rest share the line's run
+        let shared = self.lines.last().is_some_and(|run| {
+            run.location == location
+                || !instruction.can_fail() && run.location.line == location.line
+        });
+
+        if !shared {
+            self.lines.push(LineRun::new(self.code.len(), location));
+        }
+        self.code.push(instruction as u8);
     }
 
-    pub fn write_byte(&mut self, byte: u8, line: usize) {
+    pub fn write_byte(&mut self, byte: u8) {
         self.code.push(byte);
-        self.lines.push(line);
     }
 
     pub fn write_byte_at(&mut self, byte: u8, offset: usize) {
         self.code[offset] = byte;
     }
 
+    pub fn truncate(&mut self, len: usize) {
+        self.code.truncate(len);
+        self.lines.retain(|run| run.start() < len);
+    }
+
+    pub fn truncate_constants(&mut self, len: usize) {
+        self.constants.truncate(len);
+    }
+
     pub fn add_constant(&mut self, value: Value) -> usize {
         self.constants.push(value);
         self.constants.len() - 1
@@ -40,23 +157,32 @@ impl Chunk {
         self.code.get(offset).cloned()
     }
 
-    pub fn read_constant(&self, index: u8) -> Option<Value> {
-        self.constants.get(index as usize).cloned()
+    pub fn read_constant(&self, index: usize) -> Option<Value> {
+        self.constants.get(index).cloned()
     }
 
-    pub fn line(&self, pos: i32) -> usize {
-        if pos.is_negative() {
-            let len = self.lines.len();
-            self.lines[len - pos.unsigned_abs() as usize]
-        } else {
-            self.lines[pos as usize]
-        }
+    pub fn code(&self) -> &[u8] {
+        &self.code
     }
 
-    pub fn len(&self) -> usize {
-        #[cfg(debug_assertions)]
-        assert_eq!(self.code.len(), self.lines.len(), "Chunk Error — Size mismatch");
+    pub fn constants(&self) -> &[Value] {
+        &self.constants
+    }
+
+    pub fn lines(&self) -> &[LineRun] {
+        &self.lines
+    }
+
+    pub fn location(&self, offset: usize) -> Location {
+        let run = self.lines.partition_point(|run| run.start() <= offset);
+        self.lines[run.saturating_sub(1)].location
+    }
+
+    pub fn line(&self, offset: usize) -> usize {
+        self.location(offset).line()
+    }
 
+    pub fn len(&self) -> usize {
         self.code.len()
     }
 
@@ -71,67 +197,131 @@ impl Default for Chunk {
     }
 }
 
-#[cfg(debug_assertions)]
 impl Chunk {
-    pub fn disassemble(&self, name: &str) {
-        println!("== {} ==", name);
+    pub fn disassemble<W: Write>(&self, name: &str, heap: &Heap, out: &mut W) -> io::Result<()> {
+        writeln!(out, "== {} ==", name)?;
 
         let mut offset = 0;
         while let Some(byte) = self.read(offset) {
-            offset = self.disassemble_instruction(byte, offset);
+            offset = self.disassemble_instruction(byte, offset, heap, out)?;
         }
+
+        Ok(())
     }
 
-    pub fn disassemble_instruction(&self, byte: u8, offset: usize) -> usize {
+    pub fn disassemble_instruction<W: Write>(
+        &self,
+        byte: u8,
+        offset: usize,
+        heap: &Heap,
+        out: &mut W,
+    ) -> io::Result<usize> {
         use crate::repr::error::LoxResult;
         use Instruction::*;
 
         let maybe_instruction: LoxResult<Instruction> = byte.try_into();
 
-        match maybe_instruction {
-            Ok(instruction) => {
-                print!("{:04} ", offset);
-                if offset > 0 && self.lines[offset] == self.lines[offset - 1] {
-                    print!("   | ")
-                } else {
-                    print!("{:>4} ", self.lines[offset])
+        let instruction = match maybe_instruction {
+            Ok(instruction) => instruction,
+
+            Err(e) => {
+                writeln!(out, "{e}")?;
+                return Ok(offset + 1);
+            }
+        };
+
+        write!(out, "{:04} ", offset)?;
+        if offset > 0 && self.line(offset) == self.line(offset - 1) {
+            write!(out, "   | ")?;
+        } else {
+            write!(out, "{:>4} ", self.line(offset))?;
+        };
+
+        let name = format!("{:?}", instruction);
+
+        let next = match instruction {
+            Constant | ConstantLong | GetProperty | GetPropertyLong | SetProperty
+            | SetPropertyLong | Class | ClassLong | Method | MethodLong => {
+                let (index, next) = self.constant_operand(instruction, offset);
+                let constant = heap.display(self.constants[index]);
+                writeln!(out, "{:<16} {:>4} '{}'", name, index, constant)?;
+                next
+            }
+
+            DefineGlobal | DefineGlobalLong | SetGlobal | SetGlobalLong | GetGlobal
+            | GetGlobalLong => {
+                let (slot, next) = self.constant_operand(instruction, offset);
+                let global = heap.global_name(slot).map_or("?", |name| heap.string(name));
+                writeln!(out, "{:<16} {:>4} '{}'", name, slot, global)?;
+                next
+            }
+
+            GetLocal | SetLocal | GetUpvalue | SetUpvalue | Call | PopN => {
+                let operand = self.code[offset + 1];
+                writeln!(out, "{:<16} {:>4}", name, operand)?;
+                offset + 2
+            }
+
+            Closure | ClosureLong => {
+                let (index, next) = self.constant_operand(instruction, offset);
+                let constant = self.constants[index];
+                writeln!(out, "{:<16} {:>4} {}", name, index, heap.display(constant))?;
+
+                let Value::Object(function) = constant else {
+                    return Ok(next);
                 };
 
-                match instruction {
-                    Constant | DefineGlobal | SetGlobal | GetGlobal => {
-                        let index = self.code[offset + 1];
-                        let constant = self.constants[index as usize].clone();
-                        println!("{:<16?} {:>4} '{}'", instruction, index, constant);
-                        offset + 2
-                    }
-
-                    GetLocal | SetLocal => {
-                        let slot = self.code[offset + 1];
-                        println!("{:<16?} {:>4}", self, slot);
-                        offset + 2
-                    }
-
-                    Jump | JumpIfFalse => {
-                        let addr_a = self.code[offset + 1];
-                        let addr_b = self.code[offset + 2];
-
-                        let jump = u16::from_be_bytes([addr_a, addr_b]);
-
-                        println!("{:<16?} {:>4} -> {}", instruction, offset, offset + 3 + jump as usize);
-                        offset + 3
-                    }
-
-                    _ => {
-                        println!("{:?}", instruction);
-                        offset + 1
-                    }
+                let mut offset = next;
+                for _ in 0..heap.function(function).upvalue_count() {
+                    let is_local = self.code[offset];
+                    let index = self.code[offset + 1];
+                    let kind = if is_local == 1 { "local" } else { "upvalue" };
+                    writeln!(
+                        out,
+                        "{:04}    |                     {} {}",
+                        offset, kind, index
+                    )?;
+                    offset += 2;
                 }
+
+                offset
             }
 
-            Err(e) => {
-                println!("{e}");
+            Jump | JumpIfFalse | Loop => {
+                let addr_a = self.code[offset + 1];
+                let addr_b = self.code[offset + 2];
+
+                let jump = u16::from_be_bytes([addr_a, addr_b]) as usize;
+                let target = if let Loop = instruction {
+                    offset + 3 - jump
+                } else {
+                    offset + 3 + jump
+                };
+
+                writeln!(out, "{:<16} {:>4} -> {}", name, offset, target)?;
+                offset + 3
+            }
+
+            _ => {
+                writeln!(out, "{}", name)?;
                 offset + 1
             }
+        };
+
+        Ok(next)
+    }
+
+    fn constant_operand(&self, instruction: Instruction, offset: usize) -> (usize, usize) {
+        if instruction.is_long() {
+            let index = u32::from_be_bytes([
+                0,
+                self.code[offset + 1],
+                self.code[offset + 2],
+                self.code[offset + 3],
+            ]);
+            (index as usize, offset + 4)
+        } else {
+            (self.code[offset + 1] as usize, offset + 2)
         }
     }
 }
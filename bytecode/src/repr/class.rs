@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+
+use super::{object::ObjRef, value::Value};
+
+#[derive(Debug)]
+pub struct Class {
+    name: String,
+    methods: HashMap<ObjRef, ObjRef>,
+}
+
+impl Class {
+    pub fn new(name: String) -> Self {
+        Class {
+            name,
+            methods: HashMap::new(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn method(&self, name: ObjRef) -> Option<ObjRef> {
+        self.methods.get(&name).copied()
+    }
+
+    pub fn methods(&self) -> &HashMap<ObjRef, ObjRef> {
+        &self.methods
+    }
+
+    pub fn define_method(&mut self, name: ObjRef, method: ObjRef) {
+        self.methods.insert(name, method);
+    }
+}
+
+#[derive(Debug)]
+pub struct Instance {
+    class: ObjRef,
+    fields: HashMap<ObjRef, Value>,
+    frozen: bool,
+}
+
+impl Instance {
+    pub fn new(class: ObjRef) -> Self {
+        Instance {
+            class,
+            fields: HashMap::new(),
+            frozen: false,
+        }
+    }
+
+    pub fn class(&self) -> ObjRef {
+        self.class
+    }
+
+    pub fn field(&self, name: ObjRef) -> Option<Value> {
+        self.fields.get(&name).copied()
+    }
+
+    pub fn fields(&self) -> &HashMap<ObjRef, Value> {
+        &self.fields
+    }
+
+    pub fn set_field(&mut self, name: ObjRef, value: Value) {
+        self.fields.insert(name, value);
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.frozen
+    }
+
+    pub fn freeze(&mut self) {
+        self.frozen = true;
+    }
+}
+
+#[derive(Debug)]
+pub struct BoundMethod {
+    receiver: Value,
+    method: ObjRef,
+}
+
+impl BoundMethod {
+    pub fn new(receiver: Value, method: ObjRef) -> Self {
+        BoundMethod { receiver, method }
+    }
+
+    pub fn receiver(&self) -> Value {
+        self.receiver
+    }
+
+    pub fn method(&self) -> ObjRef {
+        self.method
+    }
+}
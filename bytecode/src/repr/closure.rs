@@ -0,0 +1,35 @@
+use super::{object::ObjRef, value::Value};
+
+#[derive(Debug)]
+pub enum Upvalue {
+    Open(usize),
+    Closed(Value),
+}
+
+#[derive(Debug)]
+pub struct Closure {
+    function: ObjRef,
+    upvalues: Vec<ObjRef>,
+}
+
+impl Closure {
+    pub fn new(function: ObjRef, upvalues: Vec<ObjRef>) -> Self {
+        Closure { function, upvalues }
+    }
+
+    pub fn function(&self) -> ObjRef {
+        self.function
+    }
+
+    pub fn upvalue(&self, index: usize) -> Option<ObjRef> {
+        self.upvalues.get(index).copied()
+    }
+
+    pub fn upvalues(&self) -> &[ObjRef] {
+        &self.upvalues
+    }
+
+    pub fn upvalue_count(&self) -> usize {
+        self.upvalues.len()
+    }
+}
@@ -3,25 +3,48 @@ use std::{
     process::{ExitCode, Termination},
 };
 
+pub use shared::interrupt::Interrupt;
+
+use super::{chunk::Location, token::Span};
+
 pub type LoxResult<T> = Result<T, LoxError>;
 
 #[derive(Debug)]
 pub enum LoxError {
     IncorrectArgumentsError,
     FileNotFoundError(String),
+    IoError(String),
+    BytecodeError(String),
+    VerifyError(VerifyError),
 
-    CompileError,
-    RuntimeError,
+    CompileError(Vec<Diagnostic>),
+    RuntimeError(RuntimeError),
+    InterruptError(Interrupt),
 }
 
 impl Display for LoxError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         use LoxError::*;
         let repr = match self {
-            CompileError | RuntimeError => format!("{:?}", self),
+            CompileError(diagnostics) => diagnostics
+                .iter()
+                .map(Diagnostic::to_string)
+                .collect::<Vec<_>>()
+                .join("\n"),
+            RuntimeError(error) => format!("{}", error),
+            InterruptError(interrupt) => format!("{}", interrupt),
 
-            IncorrectArgumentsError => String::from("Usage: klox [script]"),
+            IncorrectArgumentsError => String::from(concat!(
+                "Usage: klox [-O] [script]\n",
+                "       klox [-O] --disassemble [script]\n",
+                "       klox [-O] --trace[=start-end] [script]\n",
+                "       klox [-O] compile [--embed-source] [script] -o [output]\n",
+                "       klox [-O] bench [--engine=bytecode|treewalk] [--iterations=N] [--json] [script...]"
+            )),
             FileNotFoundError(path) => format!("File not found '{}'", path),
+            IoError(message) => format!("IO error: {}", message),
+            BytecodeError(message) => format!("Invalid bytecode: {}", message),
+            VerifyError(error) => format!("Invalid bytecode: {}", error),
         };
 
         write!(f, "{}", repr)
@@ -32,9 +55,9 @@ impl Termination for LoxError {
     fn report(self) -> ExitCode {
         let code = match self {
             LoxError::IncorrectArgumentsError => 64,
-            LoxError::FileNotFoundError(_) => 74,
-            LoxError::CompileError => 65,
-            LoxError::RuntimeError => 70,
+            LoxError::FileNotFoundError(_) | LoxError::IoError(_) => 74,
+            LoxError::CompileError(_) | LoxError::BytecodeError(_) | LoxError::VerifyError(_) => 65,
+            LoxError::RuntimeError(_) | LoxError::InterruptError(_) => 70,
         };
 
         ExitCode::from(code)
@@ -42,3 +65,294 @@ impl Termination for LoxError {
 }
 
 impl std::error::Error for LoxError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "Error"),
+            Severity::Warning => write!(f, "Warning"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Offender {
+    Lexeme(String),
+    End,
+    None,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    severity: Severity,
+    span: Span,
+    offender: Offender,
+    message: String,
+    snippet: String,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, span: Span, offender: Offender, message: String) -> Self {
+        Diagnostic {
+            severity,
+            span,
+            offender,
+            message,
+            snippet: String::new(),
+        }
+    }
+
+    pub fn with_source(self, source: &str) -> Self {
+        Diagnostic {
+            snippet: self.span.underline(source),
+            ..self
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    pub fn line(&self) -> usize {
+        self.span.line()
+    }
+
+    pub fn column(&self) -> usize {
+        self.span.column()
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn offender(&self) -> &Offender {
+        &self.offender
+    }
+
+    pub fn lexeme(&self) -> Option<&str> {
+        match &self.offender {
+            Offender::Lexeme(lexeme) => Some(lexeme),
+            _ => None,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[line {}] {}", self.span.line(), self.severity)?;
+
+        match &self.offender {
+            Offender::Lexeme(lexeme) => write!(f, " at '{}'", lexeme)?,
+            Offender::End => write!(f, " at end")?,
+            Offender::None => (),
+        }
+
+        write!(f, ": {}", self.message)?;
+
+        if !self.snippet.is_empty() {
+            write!(f, "\n{}", self.snippet)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeError {
+    message: String,
+    trace: Vec<TraceFrame>,
+    snippet: String,
+}
+
+impl RuntimeError {
+    pub fn new(message: String, trace: Vec<TraceFrame>) -> Self {
+        RuntimeError {
+            message,
+            trace,
+            snippet: String::new(),
+        }
+    }
+
+    /// Underlines where the innermost frame failed
+    pub fn with_source(self, source: &str) -> Self {
+        let snippet = self
+            .location()
+            .map_or_else(String::new, |location| location.underline(source));
+
+        RuntimeError { snippet, ..self }
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn line(&self) -> Option<usize> {
+        self.trace.first().map(TraceFrame::line)
+    }
+
+    pub fn location(&self) -> Option<Location> {
+        self.trace.first().map(TraceFrame::location)
+    }
+
+    pub fn trace(&self) -> &[TraceFrame] {
+        &self.trace
+    }
+}
+
+impl Display for RuntimeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)?;
+
+        if !self.snippet.is_empty() {
+            write!(f, "\n{}", self.snippet)?;
+        }
+
+        // Deep recursion repeats the same frame many times over
+        let mut frames = self.trace.iter().peekable();
+        while let Some(frame) = frames.next() {
+            write!(f, "\n{}", frame)?;
+
+            let mut repeats = 0;
+            while frames.next_if_eq(&frame).is_some() {
+                repeats += 1;
+            }
+            if repeats > 0 {
+                write!(f, "\n... {} more", repeats)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceFrame {
+    function: Option<String>,
+    location: Location,
+}
+
+impl TraceFrame {
+    pub fn new(function: Option<String>, location: Location) -> Self {
+        TraceFrame { function, location }
+    }
+
+    pub fn function(&self) -> Option<&str> {
+        self.function.as_deref()
+    }
+
+    pub fn line(&self) -> usize {
+        self.location.line()
+    }
+
+    pub fn column(&self) -> usize {
+        self.location.column()
+    }
+
+    pub fn location(&self) -> Location {
+        self.location
+    }
+}
+
+impl Display for TraceFrame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.function {
+            Some(name) => write!(f, "[line {}] in {}()", self.line(), name),
+            None => write!(f, "[line {}] in script", self.line()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerifyError {
+    function: Option<String>,
+    offset: usize,
+    kind: VerifyErrorKind,
+}
+
+impl VerifyError {
+    pub fn new(function: Option<&str>, offset: usize, kind: VerifyErrorKind) -> Self {
+        VerifyError {
+            function: function.map(str::to_string),
+            offset,
+            kind,
+        }
+    }
+
+    pub fn function(&self) -> Option<&str> {
+        self.function.as_deref()
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn kind(&self) -> &VerifyErrorKind {
+        &self.kind
+    }
+}
+
+impl Display for VerifyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at offset {} in ", self.kind, self.offset)?;
+
+        match &self.function {
+            Some(name) => write!(f, "{}()", name),
+            None => write!(f, "script"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum VerifyErrorKind {
+    UnknownOpcode(u8),
+    TruncatedInstruction,
+    ConstantOutOfRange(usize),
+    ExpectedString(usize),
+    ExpectedFunction(usize),
+    InvalidJumpTarget(usize),
+    InvalidLocal(usize),
+    InvalidUpvalue(usize),
+    InvalidGlobal(usize),
+    ScriptUpvalues(usize),
+    StackUnderflow,
+    StackMismatch { expected: usize, found: usize },
+    MissingReturn,
+    InvalidLineTable,
+    NestedTooDeeply,
+}
+
+impl Display for VerifyErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use VerifyErrorKind::*;
+
+        match self {
+            UnknownOpcode(byte) => write!(f, "Unknown opcode {}", byte),
+            TruncatedInstruction => write!(f, "Truncated instruction"),
+            ConstantOutOfRange(index) => write!(f, "Constant {} out of range", index),
+            ExpectedString(index) => write!(f, "Constant {} is not a string", index),
+            ExpectedFunction(index) => write!(f, "Constant {} is not a function", index),
+            InvalidJumpTarget(target) => write!(f, "Jump to {} is not an instruction", target),
+            InvalidLocal(slot) => write!(f, "Local slot {} out of range", slot),
+            InvalidUpvalue(index) => write!(f, "Upvalue {} out of range", index),
+            InvalidGlobal(slot) => write!(f, "Global slot {} out of range", slot),
+            ScriptUpvalues(count) => write!(f, "Script captures {} upvalues", count),
+            StackUnderflow => write!(f, "Stack underflow"),
+            StackMismatch { expected, found } => {
+                write!(f, "Stack depth {} does not match {}", found, expected)
+            }
+            MissingReturn => write!(f, "Missing return"),
+            InvalidLineTable => write!(f, "Invalid line table"),
+            NestedTooDeeply => write!(f, "Functions nested too deeply"),
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use std::rc::Rc;
+
+use super::chunk::Chunk;
+
+#[derive(Debug, Default)]
+pub struct Function {
+    arity: usize,
+    upvalue_count: usize,
+    chunk: Chunk,
+    name: Option<String>,
+    source: Option<Rc<str>>,
+}
+
+impl Function {
+    pub fn new(name: Option<String>) -> Self {
+        Function {
+            arity: 0,
+            upvalue_count: 0,
+            chunk: Chunk::new(),
+            name,
+            source: None,
+        }
+    }
+
+    pub fn arity(&self) -> usize {
+        self.arity
+    }
+
+    pub fn set_arity(&mut self, arity: usize) {
+        self.arity = arity;
+    }
+
+    pub fn upvalue_count(&self) -> usize {
+        self.upvalue_count
+    }
+
+    pub fn set_upvalue_count(&mut self, upvalue_count: usize) {
+        self.upvalue_count = upvalue_count;
+    }
+
+    pub fn chunk(&self) -> &Chunk {
+        &self.chunk
+    }
+
+    pub fn chunk_mut(&mut self) -> &mut Chunk {
+        &mut self.chunk
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// The script the function was compiled from, if it wasn't loaded from bytecode
+    pub fn source(&self) -> Option<&str> {
+        self.source.as_deref()
+    }
+
+    pub fn set_source(&mut self, source: Rc<str>) {
+        self.source = Some(source);
+    }
+}
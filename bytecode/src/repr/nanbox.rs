@@ -0,0 +1,59 @@
+use super::{object::ObjRef, value::Value};
+
+// Anything that isn't a number is hidden in the payload of a quiet NaN.
+// Objects also set the sign bit, and keep their heap index in the low bits
+const QNAN: u64 = 0x7ffc_0000_0000_0000;
+const SIGN: u64 = 0x8000_0000_0000_0000;
+
+const TAG_NIL: u64 = 1;
+const TAG_FALSE: u64 = 2;
+const TAG_TRUE: u64 = 3;
+
+const NIL: u64 = QNAN | TAG_NIL;
+const FALSE: u64 = QNAN | TAG_FALSE;
+const TRUE: u64 = QNAN | TAG_TRUE;
+
+const OBJECT: u64 = SIGN | QNAN;
+const INDEX_MAX: u64 = !OBJECT;
+
+/// A [`Value`] NaN-boxed into a single word
+#[derive(Debug, Clone, Copy)]
+pub struct PackedValue(u64);
+
+impl From<Value> for PackedValue {
+    fn from(value: Value) -> Self {
+        let bits = match value {
+            // Every NaN is stored the same way so none of them can look like a tag
+            Value::Number(number) if number.is_nan() => f64::NAN.to_bits(),
+            Value::Number(number) => number.to_bits(),
+            Value::Boolean(true) => TRUE,
+            Value::Boolean(false) => FALSE,
+            Value::Nil => NIL,
+            Value::Object(object) => {
+                let index = object.index() as u64;
+                debug_assert!(index <= INDEX_MAX, "Heap index too large to NaN-box");
+                OBJECT | index
+            }
+        };
+
+        PackedValue(bits)
+    }
+}
+
+impl From<PackedValue> for Value {
+    fn from(packed: PackedValue) -> Self {
+        let PackedValue(bits) = packed;
+
+        if bits & QNAN != QNAN {
+            return Value::Number(f64::from_bits(bits));
+        }
+
+        match bits {
+            NIL => Value::Nil,
+            FALSE => Value::Boolean(false),
+            TRUE => Value::Boolean(true),
+            _ if bits & OBJECT == OBJECT => Value::Object(ObjRef::new((bits & INDEX_MAX) as usize)),
+            _ => unreachable!("Invalid NaN-boxed value {bits:#x}"),
+        }
+    }
+}
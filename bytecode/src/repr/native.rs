@@ -0,0 +1,75 @@
+use std::{cell::RefCell, fmt::Debug, rc::Rc};
+
+use crate::exec::{
+    convert::{FromValue, IntoValue},
+    heap::Heap,
+};
+
+use super::value::Value;
+
+pub type NativeFn = Box<dyn FnMut(&mut NativeCtx, &[Value]) -> Result<Value, String>>;
+
+/// What a native can reach of the VM while it runs
+pub struct NativeCtx<'h> {
+    heap: &'h mut Heap,
+}
+
+impl<'h> NativeCtx<'h> {
+    pub(crate) fn new(heap: &'h mut Heap) -> Self {
+        NativeCtx { heap }
+    }
+
+    pub(crate) fn heap(&mut self) -> &mut Heap {
+        self.heap
+    }
+
+    /// Reads an argument, with the runtime error to raise if it has the wrong type
+    pub fn get<T: FromValue>(&self, value: Value) -> Result<T, String> {
+        T::from_value(value, self.heap).ok_or_else(|| {
+            format!(
+                "Expected {} but got {}.",
+                T::EXPECTED,
+                self.heap.display(value)
+            )
+        })
+    }
+
+    pub fn value<T: IntoValue>(&mut self, value: T) -> Value {
+        value.into_value(self.heap)
+    }
+}
+
+pub struct Native {
+    name: String,
+    arity: usize,
+    function: Rc<RefCell<NativeFn>>,
+}
+
+impl Native {
+    pub fn new(name: String, arity: usize, function: NativeFn) -> Self {
+        Native {
+            name,
+            arity,
+            function: Rc::new(RefCell::new(function)),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn arity(&self) -> usize {
+        self.arity
+    }
+
+    /// Shared so the VM can call it while the heap is borrowed for the native
+    pub fn function(&self) -> Rc<RefCell<NativeFn>> {
+        Rc::clone(&self.function)
+    }
+}
+
+impl Debug for Native {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Native({})", self.name)
+    }
+}
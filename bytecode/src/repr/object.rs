@@ -0,0 +1,53 @@
+use std::mem::size_of;
+
+use super::{
+    class::{BoundMethod, Class, Instance},
+    closure::{Closure, Upvalue},
+    function::Function,
+    native::Native,
+    value::Value,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ObjRef(usize);
+
+impl ObjRef {
+    pub fn new(index: usize) -> Self {
+        ObjRef(index)
+    }
+
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+#[derive(Debug)]
+pub enum Object {
+    String(String),
+    Function(Function),
+    Native(Native),
+    Closure(Closure),
+    Upvalue(Upvalue),
+    Class(Class),
+    Instance(Instance),
+    BoundMethod(BoundMethod),
+}
+
+impl Object {
+    pub fn size(&self) -> usize {
+        let payload = match self {
+            Object::String(string) => string.capacity(),
+            Object::Function(function) => {
+                let chunk = function.chunk();
+                chunk.len() + size_of_val(chunk.lines()) + size_of_val(chunk.constants())
+            }
+            Object::Native(native) => native.name().len(),
+            Object::Closure(closure) => closure.upvalue_count() * size_of::<ObjRef>(),
+            Object::Upvalue(_) | Object::BoundMethod(_) => 0,
+            Object::Class(class) => class.methods().len() * size_of::<(ObjRef, ObjRef)>(),
+            Object::Instance(instance) => instance.fields().len() * size_of::<(ObjRef, Value)>(),
+        };
+
+        size_of::<Object>() + payload
+    }
+}
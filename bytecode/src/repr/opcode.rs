@@ -4,14 +4,18 @@ use super::error::LoxError;
 #[repr(u8)]
 pub enum Instruction {
     Constant,
+    ConstantLong,
 
     Nil,
     True,
     False,
 
     Equal,
+    NotEqual,
     Greater,
+    GreaterEqual,
     Less,
+    LessEqual,
 
     Add,
     Subtract,
@@ -23,23 +27,121 @@ pub enum Instruction {
 
     Print,
     Pop,
+    PopN,
 
     DefineGlobal,
+    DefineGlobalLong,
     GetLocal,
     SetLocal,
     SetGlobal,
+    SetGlobalLong,
     GetGlobal,
+    GetGlobalLong,
+    GetUpvalue,
+    SetUpvalue,
+    GetProperty,
+    GetPropertyLong,
+    SetProperty,
+    SetPropertyLong,
 
     Jump,
     JumpIfFalse,
 
     Loop,
-    
+
+    Call,
+    Closure,
+    ClosureLong,
+    CloseUpvalue,
+
+    Class,
+    ClassLong,
+    Method,
+    MethodLong,
+
     Return,
 }
 
 const MAX_OPCODE: u8 = Instruction::Return as u8;
 
+impl Instruction {
+    pub fn long(self) -> Option<Instruction> {
+        use Instruction::*;
+
+        match self {
+            Constant => Some(ConstantLong),
+            DefineGlobal => Some(DefineGlobalLong),
+            SetGlobal => Some(SetGlobalLong),
+            GetGlobal => Some(GetGlobalLong),
+            GetProperty => Some(GetPropertyLong),
+            SetProperty => Some(SetPropertyLong),
+            Closure => Some(ClosureLong),
+            Class => Some(ClassLong),
+            Method => Some(MethodLong),
+            _ => None,
+        }
+    }
+
+    pub fn operand_len(self) -> usize {
+        use Instruction::*;
+
+        match self {
+            _ if self.is_long() => 3,
+
+            Constant | DefineGlobal | SetGlobal | GetGlobal | GetProperty | SetProperty | Class
+            | Method | Closure | GetLocal | SetLocal | GetUpvalue | SetUpvalue | Call | PopN => 1,
+
+            Jump | JumpIfFalse | Loop => 2,
+
+            _ => 0,
+        }
+    }
+
+    /// Whether the instruction can raise a runtime error, and so needs an exact location
+    pub fn can_fail(self) -> bool {
+        use Instruction::*;
+
+        matches!(
+            self,
+            Greater
+                | GreaterEqual
+                | Less
+                | LessEqual
+                | Add
+                | Subtract
+                | Multiply
+                | Divide
+                | Negate
+                | SetGlobal
+                | SetGlobalLong
+                | GetGlobal
+                | GetGlobalLong
+                | GetProperty
+                | GetPropertyLong
+                | SetProperty
+                | SetPropertyLong
+                | Call
+        )
+    }
+
+    pub fn is_long(self) -> bool {
+        use Instruction::*;
+
+        matches!(
+            self,
+            ConstantLong
+                | DefineGlobalLong
+                | SetGlobalLong
+                | GetGlobalLong
+                | GetPropertyLong
+                | SetPropertyLong
+                | ClosureLong
+                | ClassLong
+                | MethodLong
+        )
+    }
+}
+
 impl TryFrom<u8> for Instruction {
     type Error = LoxError;
 
@@ -49,11 +151,10 @@ impl TryFrom<u8> for Instruction {
             // MAX_OPCODE is derived from Instruction::Return, the final variant
             // Since Instruction is defined as repr(u8), the variants form a contiguous range
             // any u8 value less than or equal to Instruction::Return as u8 is a valid instruction
-            let instruction = unsafe { std::mem::transmute(value) };
+            let instruction = unsafe { std::mem::transmute::<u8, Instruction>(value) };
             Ok(instruction)
         } else {
-            eprintln!("Unknown opcode");
-            Err(LoxError::CompileError)
+            Err(LoxError::BytecodeError(format!("Unknown opcode {}", value)))
         }
     }
 }
@@ -27,10 +27,10 @@ impl TryFrom<u8> for Precedence {
             // MAX_PREC is derived from Precedence::Primary, the final variant
             // Since Instruction is defined as repr(u8), the variants form a contiguous range
             // any u8 value less than or equal to Precedence::Primary as u8 is valid
-            let prec = unsafe { std::mem::transmute(value) };
+            let prec = unsafe { std::mem::transmute::<u8, Precedence>(value) };
             Ok(prec)
         } else {
-            Err(LoxError::CompileError)
+            Err(LoxError::CompileError(vec![]))
         }
     }
 }
@@ -46,6 +46,9 @@ pub enum ParseFn {
     Variable,
     And,
     Or,
+    Call,
+    Dot,
+    This,
     Null,
 }
 
@@ -72,7 +75,8 @@ impl From<TokenType> for Rule {
     fn from(value: TokenType) -> Self {
         use TokenType::*;
         let (prefix, infix, prec) = match value {
-            LeftParen => (ParseFn::Grouping, ParseFn::Null, Precedence::Min),
+            LeftParen => (ParseFn::Grouping, ParseFn::Call, Precedence::Call),
+            Dot => (ParseFn::Null, ParseFn::Dot, Precedence::Call),
 
             Bang => (ParseFn::Unary, ParseFn::Null, Precedence::Min),
 
@@ -94,6 +98,8 @@ impl From<TokenType> for Rule {
 
             Nil | True | False => (ParseFn::Literal, ParseFn::Null, Precedence::Min),
 
+            This => (ParseFn::This, ParseFn::Null, Precedence::Min),
+
             And => (ParseFn::Null, ParseFn::And, Precedence::And),
             Or => (ParseFn::Null, ParseFn::Or, Precedence::Or),
 
@@ -1,3 +1,5 @@
+use shared::span;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TokenType {
     // Single-character tokens.
@@ -51,16 +53,62 @@ pub enum TokenType {
     Error,
 }
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Span {
+    start: usize,
+    end: usize,
+    line: usize,
+    column: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize, line: usize, column: usize) -> Self {
+        Span {
+            start,
+            end,
+            line,
+            column,
+        }
+    }
+
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            end: other.end.max(self.end),
+            ..self
+        }
+    }
+
+    pub fn underline(&self, source: &str) -> String {
+        span::underline(source, self.start, self.end)
+    }
+}
+
 #[derive(Debug)]
 pub struct Token {
     kind: TokenType,
     lexeme: String,
-    line: usize,
+    span: Span,
 }
 
 impl Token {
-    pub fn new(kind: TokenType, lexeme: String, line: usize) -> Self {
-        Token { kind, lexeme, line }
+    pub fn new(kind: TokenType, lexeme: String, span: Span) -> Self {
+        Token { kind, lexeme, span }
     }
 
     pub fn kind(&self) -> TokenType {
@@ -72,7 +120,15 @@ impl Token {
     }
 
     pub fn line(&self) -> usize {
-        self.line
+        self.span.line
+    }
+
+    pub fn column(&self) -> usize {
+        self.span.column
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
     }
 }
 
@@ -81,7 +137,7 @@ impl Default for Token {
         Token {
             kind: TokenType::Nil,
             lexeme: String::from(""),
-            line: 1,
+            span: Span::new(0, 0, 1, 1),
         }
     }
 }
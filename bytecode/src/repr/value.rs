@@ -1,10 +1,12 @@
-use std::fmt::Display;
+use shared::value::Operand;
 
-#[derive(Debug, Clone, PartialEq)]
+use super::object::ObjRef;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Value {
-    String(Box<String>),
     Number(f64),
     Boolean(bool),
+    Object(ObjRef),
     Nil,
 }
 
@@ -12,17 +14,43 @@ impl Value {
     pub fn truthy(&self) -> bool {
         !matches!(self, Value::Nil | Value::Boolean(false))
     }
+
+    pub fn as_object(&self) -> Option<ObjRef> {
+        match self {
+            Value::Object(object) => Some(*object),
+            _ => None,
+        }
+    }
+
+    /// Strings are interned, so comparing them by identity compares their contents
+    pub fn operand(&self) -> Operand<'static, ObjRef> {
+        match *self {
+            Value::Number(number) => Operand::Number(number),
+            Value::Boolean(boolean) => Operand::Boolean(boolean),
+            Value::Object(object) => Operand::Object(object),
+            Value::Nil => Operand::Nil,
+        }
+    }
 }
 
-impl Display for Value {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let repr = match self {
-            Value::Number(number) => number.to_string(),
-            Value::Boolean(boolean) => boolean.to_string(),
-            Value::Nil => String::from("nil"),
-            Value::String(boxed_string) => boxed_string.to_string(),
-        };
+#[cfg(feature = "nan-boxing")]
+pub use super::nanbox::PackedValue;
+
+/// How the VM stores a [`Value`] on its stack. Without the `nan-boxing` feature this is the enum itself
+#[cfg(not(feature = "nan-boxing"))]
+#[derive(Debug, Clone, Copy)]
+pub struct PackedValue(Value);
+
+#[cfg(not(feature = "nan-boxing"))]
+impl From<Value> for PackedValue {
+    fn from(value: Value) -> Self {
+        PackedValue(value)
+    }
+}
 
-        write!(f, "{}", repr)
+#[cfg(not(feature = "nan-boxing"))]
+impl From<PackedValue> for Value {
+    fn from(packed: PackedValue) -> Self {
+        packed.0
     }
 }
@@ -0,0 +1,58 @@
+use std::{path::Path, process::Command};
+
+fn bench(args: &[&str], scripts: &[&str]) -> String {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+    let output = Command::new(env!("CARGO_BIN_EXE_bytecode"))
+        .current_dir(root)
+        .arg("bench")
+        .arg("--iterations=2")
+        .args(args)
+        .args(scripts)
+        .output()
+        .expect("Failed to run klox");
+
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).expect("Non UTF-8 output")
+}
+
+#[test]
+fn json_counts_ops_for_every_engine() {
+    let scripts = ["lox/fun.lox", "bench/gc.lox"];
+
+    let bytecode = bench(&["--json"], &scripts);
+    assert!(
+        bytecode.starts_with("{\"engine\":\"bytecode\","),
+        "{bytecode}"
+    );
+    assert!(bytecode.contains("\"script\":\"lox/fun.lox\",\"mean_ns\":"));
+    assert_eq!(bytecode.matches(",\"ops\":").count(), 2, "{bytecode}");
+
+    let treewalk = bench(&["--json", "--engine=treewalk"], &scripts);
+    assert!(
+        treewalk.starts_with("{\"engine\":\"treewalk\","),
+        "{treewalk}"
+    );
+    assert_eq!(treewalk.matches(",\"ops\":").count(), 1, "{treewalk}");
+    assert!(treewalk.contains("{\"script\":\"bench/gc.lox\",\"skipped\":true}"));
+}
+
+#[test]
+fn table_fits_the_longest_path() {
+    let long = "lox/../bench/../lox/../bench/../lox/fun.lox";
+    let text = bench(&[], &["lox/if.lox", long]);
+    let lines: Vec<&str> = text.lines().collect();
+
+    let width = long.len();
+    assert!(
+        lines[1].starts_with(&format!("{:<width$} ", "script")),
+        "{text}"
+    );
+    assert!(
+        lines[2].starts_with(&format!("{:<width$} ", "lox/if.lox")),
+        "{text}"
+    );
+    assert!(lines[3].starts_with(&format!("{long} ")), "{text}");
+
+    let widths: Vec<usize> = lines[1..].iter().map(|line| line.chars().count()).collect();
+    assert!(widths.iter().all(|&line| line == widths[0]), "{text}");
+}
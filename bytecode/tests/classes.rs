@@ -0,0 +1,62 @@
+mod common;
+
+use bytecode::repr::error::LoxError;
+use common::{output, vm};
+
+#[test]
+fn instances_fields_and_methods() {
+    let source = "
+class Point {
+  init(x, y) { this.x = x; this.y = y; }
+  sum() { return this.x + this.y; }
+  scale(n) { this.x = this.x * n; this.y = this.y * n; return this; }
+}
+var p = Point(1, 2);
+print p.sum();
+print p.scale(3).sum();
+print p;
+print Point;
+var method = p.sum;
+p.x = 10;
+print method();
+p.sum = \"field wins\";
+print p.sum;
+class Empty {}
+var e = Empty();
+e.callback = Point(0, 1).sum;
+print e.callback();
+print Point(1, 1).init(5, 5).x;
+";
+
+    assert_eq!(
+        output(source),
+        "3\n9\nPoint instance\nPoint\n16\nfield wins\n1\n5\n"
+    );
+}
+
+#[test]
+fn bad_property_access_is_a_runtime_error() {
+    let cases = [
+        ("class A {} A().missing;", "Undefined property 'missing'."),
+        ("class A {} A(1);", "Expected 0 arguments but got 1."),
+        ("1.field;", "Only instances have properties."),
+        ("1.field = 2;", "Only instances have fields."),
+    ];
+
+    for (source, message) in cases {
+        let Err(LoxError::RuntimeError(error)) = vm().interpret(source) else {
+            panic!("Expected a runtime error from {source}");
+        };
+        assert_eq!(error.message(), message);
+    }
+}
+
+#[test]
+fn misplaced_this_and_initializer_returns_are_compile_errors() {
+    for source in ["print this;", "class A { init() { return 1; } }"] {
+        assert!(matches!(
+            vm().interpret(source),
+            Err(LoxError::CompileError(_))
+        ));
+    }
+}
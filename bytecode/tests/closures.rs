@@ -0,0 +1,70 @@
+mod common;
+
+use common::output;
+
+#[test]
+fn counters_keep_their_own_state() {
+    let source = "
+fun makeCounter() {
+  var count = 0;
+  fun increment() { count = count + 1; return count; }
+  return increment;
+}
+var a = makeCounter();
+var b = makeCounter();
+print a();
+print a();
+print b();
+";
+
+    assert_eq!(output(source), "1\n2\n1\n");
+}
+
+#[test]
+fn closures_share_captured_variables() {
+    let source = "
+var get;
+var set;
+fun pair() {
+  var shared = \"initial\";
+  fun g() { return shared; }
+  fun s(value) { shared = value; }
+  get = g;
+  set = s;
+}
+pair();
+set(\"updated\");
+print get();
+";
+
+    assert_eq!(output(source), "updated\n");
+}
+
+#[test]
+fn captures_outlive_their_scope() {
+    let source = "
+var closures = nil;
+{
+  var first = \"first\";
+  fun outer() {
+    fun inner() { return first; }
+    return inner;
+  }
+  closures = outer();
+}
+print closures();
+
+fun loop() {
+  var fns = nil;
+  for (var i = 0; i < 3; i = i + 1) {
+    var j = i;
+    fun capture() { return j; }
+    if (i == 1) fns = capture;
+  }
+  return fns;
+}
+print loop()();
+";
+
+    assert_eq!(output(source), "first\n1\n");
+}
@@ -0,0 +1,23 @@
+// Each test binary only uses some of these helpers
+#![allow(dead_code)]
+
+use std::io;
+
+use bytecode::exec::vm::VirtualMachine;
+use shared::io::Capture;
+
+/// A VM with its output discarded and nothing to read
+pub fn vm() -> VirtualMachine {
+    VirtualMachine::with_streams(io::sink(), io::sink(), io::empty())
+}
+
+/// Runs a script that should succeed, returning what it printed
+pub fn output(source: &str) -> String {
+    let stdout = Capture::default();
+    let mut vm = VirtualMachine::with_streams(stdout.clone(), io::sink(), io::empty());
+
+    if let Err(error) = vm.interpret(source) {
+        panic!("{error}");
+    }
+    stdout.contents()
+}
@@ -0,0 +1,60 @@
+mod common;
+
+use std::io;
+
+use bytecode::exec::vm::VirtualMachine;
+use common::{output, vm};
+use shared::io::Capture;
+
+const COUNT: usize = 300;
+
+/// A script past every one-byte operand: constants, global slots and method names
+fn wide_script() -> String {
+    let mut source = String::new();
+
+    for index in 0..COUNT {
+        source.push_str(&format!("var g{index} = {index}.5;\n"));
+    }
+    source.push_str("var sum = 0;\n");
+    for index in 0..COUNT {
+        source.push_str(&format!("sum = sum + g{index};\n"));
+    }
+    source.push_str("print sum;\n");
+
+    source.push_str("class Wide {\n");
+    for index in 0..COUNT {
+        source.push_str(&format!("  m{index}() {{ return \"method {index}\"; }}\n"));
+    }
+    source.push_str("}\n");
+    source.push_str(&format!(
+        "var wide = Wide();\nprint wide.m0();\nprint wide.m{}();\n",
+        COUNT - 1
+    ));
+    source.push_str(&format!(
+        "wide.f{COUNT} = g{};\nprint wide.f{COUNT};\n",
+        COUNT - 1
+    ));
+
+    source
+}
+
+fn expected() -> String {
+    let sum: f64 = (0..COUNT).map(|index| index as f64 + 0.5).sum();
+    format!("{sum}\nmethod 0\nmethod {}\n{}.5\n", COUNT - 1, COUNT - 1)
+}
+
+#[test]
+fn operands_widen_past_one_byte() {
+    assert_eq!(output(&wide_script()), expected());
+}
+
+#[test]
+fn wide_operands_survive_serialization() {
+    let bytes = vm().compile(&wide_script()).unwrap();
+
+    let stdout = Capture::default();
+    let mut loaded = VirtualMachine::with_streams(stdout.clone(), io::sink(), io::empty());
+    loaded.interpret_bytecode(&bytes).unwrap();
+
+    assert_eq!(stdout.contents(), expected());
+}
@@ -0,0 +1,49 @@
+mod common;
+
+use bytecode::repr::error::{LoxError, Offender};
+use common::vm;
+
+#[test]
+fn one_compile_reports_every_error() {
+    let source = "var = 1;\nprint (;\nvar ok = @;\n{ var x; var x; }\nclass { }\n";
+
+    let Err(LoxError::CompileError(diagnostics)) = vm().interpret(source) else {
+        panic!("Expected compile errors");
+    };
+
+    let found: Vec<(usize, &str)> = diagnostics
+        .iter()
+        .map(|diagnostic| (diagnostic.line(), diagnostic.message()))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            (1, "Expect variable name"),
+            (2, "Expect expression."),
+            (3, "Unexpected character."),
+            (4, "Already a variable with this name in this scope."),
+            (5, "Expect class name."),
+        ]
+    );
+
+    assert_eq!(diagnostics[0].lexeme(), Some("="));
+    assert_eq!(*diagnostics[2].offender(), Offender::None);
+    assert_eq!(diagnostics[3].column(), 14);
+}
+
+#[test]
+fn errors_display_one_after_another() {
+    let error = vm().interpret("print;\nprint;\n").unwrap_err();
+
+    assert_eq!(
+        error.to_string(),
+        concat!(
+            "[line 1] Error at ';': Expect expression.\n",
+            "print;\n",
+            "     ^\n",
+            "[line 2] Error at ';': Expect expression.\n",
+            "print;\n",
+            "     ^"
+        )
+    );
+}
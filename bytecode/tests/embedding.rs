@@ -0,0 +1,106 @@
+mod common;
+
+use std::{cell::RefCell, rc::Rc};
+
+use bytecode::exec::convert::Handle;
+use common::vm;
+
+#[test]
+fn globals_round_trip() {
+    let mut vm = vm();
+    vm.set_global("width", 3);
+    vm.set_global("height", 4.5);
+    vm.set_global("name", "box");
+
+    vm.interpret("var area = width * height; var label = name + \"!\";")
+        .unwrap();
+
+    assert_eq!(vm.get_global::<f64>("area").unwrap(), 13.5);
+    assert_eq!(vm.get_global::<String>("label").unwrap(), "box!");
+    assert_eq!(vm.get_global::<Option<f64>>("missing").ok(), None);
+    assert!(vm.get_global::<bool>("area").is_err());
+}
+
+#[test]
+fn call_by_name_and_value() {
+    let mut vm = vm();
+    vm.set_gc_stress(true);
+    vm.interpret(
+        "fun greet(greeting, name) { return greeting + \", \" + name; }
+         fun makeAdder(n) { fun add(x) { return x + n; } return add; }
+         class Point { init(x) { this.x = x; } }
+         var adder = makeAdder(10);",
+    )
+    .unwrap();
+
+    let greeting: String = vm.call("greet", ("Hello", String::from("Lox"))).unwrap();
+    assert_eq!(greeting, "Hello, Lox");
+
+    let adder: Handle = vm.get_global("adder").unwrap();
+    assert_eq!(vm.call::<_, _, f64>(&adder, (5,)).unwrap(), 15.0);
+    assert_eq!(vm.call::<_, _, f64>("adder", vec![1.5]).unwrap(), 11.5);
+
+    assert!(vm.call::<_, _, f64>("clock", ()).is_ok());
+    assert!(vm.call::<_, _, Handle>("Point", (1,)).is_ok());
+
+    assert!(vm.call::<_, _, f64>("greet", (1,)).is_err());
+    assert!(vm.call::<_, _, f64>("nope", ()).is_err());
+
+    // The VM is still usable after a failed call
+    vm.interpret("var after = adder(1);").unwrap();
+    assert_eq!(vm.get_global::<f64>("after").unwrap(), 11.0);
+}
+
+#[test]
+fn handles_keep_objects_alive() {
+    let mut vm = vm();
+    vm.set_gc_stress(true);
+    vm.interpret(
+        "class Point { init(x) { this.x = x; } }
+         fun getX(point) { return point.x; }",
+    )
+    .unwrap();
+
+    let point: Handle = vm.call("Point", (1,)).unwrap();
+    vm.interpret("for (var i = 0; i < 100; i = i + 1) { Point(i); }")
+        .unwrap();
+
+    assert_eq!(vm.call::<_, _, f64>("getX", (&point,)).unwrap(), 1.0);
+
+    // A clone keeps the object alive after the original is dropped
+    let copy = point.clone();
+    drop(point);
+    vm.interpret("for (var i = 0; i < 100; i = i + 1) { Point(i); }")
+        .unwrap();
+    assert_eq!(vm.call::<_, _, f64>("getX", (&copy,)).unwrap(), 1.0);
+}
+
+#[test]
+fn natives_capture_host_state() {
+    let log = Rc::new(RefCell::new(vec![]));
+
+    let mut vm = vm();
+    let calls = Rc::clone(&log);
+    vm.define_native("record", 1, move |ctx, arguments| {
+        let message: String = ctx.get(arguments[0])?;
+        calls.borrow_mut().push(message);
+        Ok(ctx.value(calls.borrow().len() as f64))
+    });
+
+    vm.interpret(
+        "var count;
+         for (var i = 0; i < 3; i = i + 1) count = record(\"call \" + \"lox\");",
+    )
+    .unwrap();
+
+    assert_eq!(vm.get_global::<f64>("count").unwrap(), 3.0);
+    assert_eq!(*log.borrow(), ["call lox"; 3]);
+
+    let error = vm.interpret("record(1);").unwrap_err();
+    assert!(
+        error
+            .to_string()
+            .starts_with("Expected a string but got 1."),
+        "{error}"
+    );
+}
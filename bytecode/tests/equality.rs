@@ -0,0 +1,104 @@
+use std::io;
+
+use bytecode::exec::vm::VirtualMachine;
+use shared::io::Capture;
+use treewalk::{interpreter::Interpreter, lox::Lox};
+
+const CLOSURES: &str = "
+fun make() { fun inc() { return 1; } return inc; }
+var a = make();
+var b = make();
+print a == b;
+print a == a;
+print deepEqual(a, b);
+print deepEqual(a, a);
+";
+
+#[test]
+fn closures_compare_by_identity_in_both_engines() {
+    let expected = "false\ntrue\nfalse\ntrue\n";
+
+    let stdout = Capture::default();
+    let mut vm = VirtualMachine::with_streams(stdout.clone(), io::sink(), io::empty());
+    vm.interpret(CLOSURES).unwrap();
+    assert_eq!(stdout.contents(), expected);
+
+    let stdout = Capture::default();
+    let mut interpreter = Interpreter::with_streams(stdout.clone(), io::sink(), io::empty());
+    assert!(Lox::run(CLOSURES.to_string(), &mut interpreter));
+    assert_eq!(stdout.contents(), expected);
+}
+
+const PRIMITIVES: &str = "
+print true == true;
+print false != true;
+print nil == nil;
+print nil == false;
+print 1 == 1;
+print 0 / 0 == 0 / 0;
+print \"a\" + \"b\" == \"ab\";
+print \"1\" == 1;
+print deepEqual(\"ab\", \"a\" + \"b\");
+print deepCopy(\"ab\") == \"ab\";
+print freeze(1) == 1;
+";
+
+#[test]
+fn primitives_compare_the_same_in_both_engines() {
+    let expected = "true\ntrue\ntrue\nfalse\ntrue\nfalse\ntrue\nfalse\ntrue\ntrue\ntrue\n";
+
+    let stdout = Capture::default();
+    let mut vm = VirtualMachine::with_streams(stdout.clone(), io::sink(), io::empty());
+    vm.interpret(PRIMITIVES).unwrap();
+    assert_eq!(stdout.contents(), expected);
+
+    let stdout = Capture::default();
+    let mut interpreter = Interpreter::with_streams(stdout.clone(), io::sink(), io::empty());
+    assert!(Lox::run(PRIMITIVES.to_string(), &mut interpreter));
+    assert_eq!(stdout.contents(), expected);
+}
+
+#[test]
+fn long_lists_copy_compare_and_freeze() {
+    let source = "
+class Node { init(next) { this.next = next; } }
+var list = nil;
+for (var i = 0; i < 50000; i = i + 1) list = Node(list);
+
+var copy = deepCopy(list);
+print copy == list;
+print deepEqual(copy, list);
+
+copy.next.next = nil;
+print deepEqual(copy, list);
+
+freeze(list);
+list.next.next = nil;
+";
+
+    let stdout = Capture::default();
+    let mut vm = VirtualMachine::with_streams(stdout.clone(), io::sink(), io::empty());
+    let error = vm.interpret(source).unwrap_err();
+
+    assert_eq!(stdout.contents(), "false\ntrue\nfalse\n");
+    assert!(error.to_string().contains("frozen"), "{error}");
+}
+
+#[test]
+fn cycles_copy_and_compare() {
+    let source = "
+class Pair {}
+var a = Pair();
+a.other = Pair();
+a.other.other = a;
+
+var copy = deepCopy(a);
+print copy.other.other == copy;
+print deepEqual(copy, a);
+";
+
+    let stdout = Capture::default();
+    let mut vm = VirtualMachine::with_streams(stdout.clone(), io::sink(), io::empty());
+    vm.interpret(source).unwrap();
+    assert_eq!(stdout.contents(), "true\ntrue\n");
+}
@@ -0,0 +1,90 @@
+mod common;
+
+use bytecode::{repr::error::LoxError, FUNCTION_DEPTH_MAX};
+use common::vm;
+
+#[test]
+fn runtime_errors_underline_the_operator() {
+    let source = "var a = 1;\nvar b = \"b\";\nprint a + a * (a - b) + a;\n";
+
+    let Err(LoxError::RuntimeError(error)) = vm().interpret(source) else {
+        panic!("Expected a runtime error");
+    };
+
+    assert_eq!(error.location().map(|location| location.column()), Some(18));
+    assert_eq!(
+        error.to_string(),
+        concat!(
+            "Operands must be numbers.\n",
+            "print a + a * (a - b) + a;\n",
+            "                 ^\n",
+            "[line 3] in script"
+        )
+    );
+}
+
+#[test]
+fn runtime_errors_underline_the_innermost_frame() {
+    let source = "fun f(x) {\n  return x.field;\n}\nf(1);\n";
+
+    let Err(LoxError::RuntimeError(error)) = vm().interpret(source) else {
+        panic!("Expected a runtime error");
+    };
+
+    assert_eq!(
+        error.to_string(),
+        concat!(
+            "Only instances have properties.\n",
+            "  return x.field;\n",
+            "           ^^^^^\n",
+            "[line 2] in f()\n",
+            "[line 4] in script"
+        )
+    );
+}
+
+#[test]
+fn function_nesting_is_capped() {
+    let nested = |depth| "fun f() {".repeat(depth) + &"}".repeat(depth);
+
+    assert!(vm().interpret(&nested(FUNCTION_DEPTH_MAX)).is_ok());
+    assert!(matches!(
+        vm().interpret(&nested(FUNCTION_DEPTH_MAX + 1)),
+        Err(LoxError::CompileError(_))
+    ));
+}
+
+#[test]
+fn frame_overflow_elides_repeated_frames() {
+    let source = "fun f(n) {\n  return f(n + 1);\n}\nf(0);\n";
+
+    let mut vm = vm();
+    vm.set_frame_limit(10);
+    let Err(LoxError::RuntimeError(error)) = vm.interpret(source) else {
+        panic!("Expected a runtime error");
+    };
+
+    assert_eq!(error.message(), "Stack overflow.");
+    assert_eq!(error.trace().len(), 10);
+    assert_eq!(
+        error.to_string().lines().skip(3).collect::<Vec<_>>(),
+        ["[line 2] in f()", "... 8 more", "[line 4] in script"]
+    );
+}
+
+#[test]
+fn stack_overflow_is_a_runtime_error() {
+    let source = "fun f(a, b, c, d) { return f(a, b, c, d); }\nf(1, 2, 3, 4);\n";
+
+    let mut vm = vm();
+    vm.set_stack_limit(32);
+    let Err(LoxError::RuntimeError(error)) = vm.interpret(source) else {
+        panic!("Expected a runtime error");
+    };
+
+    assert_eq!(error.message(), "Stack overflow.");
+    assert!(error.trace().len() < 64);
+
+    // The VM recovers and can run again
+    vm.interpret("print 1;").unwrap();
+}
@@ -0,0 +1,59 @@
+mod common;
+
+use bytecode::repr::error::LoxError;
+use common::{output, vm};
+
+#[test]
+fn calls_return_values() {
+    let source = "
+fun add(a, b) { return a + b; }
+fun noReturn() {}
+fun early(n) { if (n > 0) return \"positive\"; return \"other\"; }
+fun fib(n) { if (n < 2) return n; return fib(n - 2) + fib(n - 1); }
+print add(1, 2);
+print noReturn();
+print early(1);
+print early(-1);
+print fib(15);
+print add;
+print clock;
+";
+
+    assert_eq!(
+        output(source),
+        "3\nnil\npositive\nother\n610\n<fn add>\n<native fn>\n"
+    );
+}
+
+#[test]
+fn arguments_are_locals_of_the_callee() {
+    let source = "
+var a = \"global\";
+fun shadow(a) { a = a + 1; return a; }
+print shadow(1);
+print a;
+";
+
+    assert_eq!(output(source), "2\nglobal\n");
+}
+
+#[test]
+fn bad_calls_are_runtime_errors() {
+    let arity = vm().interpret("fun f(a) {} f(1, 2);").unwrap_err();
+    assert!(arity
+        .to_string()
+        .starts_with("Expected 1 arguments but got 2."));
+
+    let callee = vm().interpret("var x = 1; x();").unwrap_err();
+    assert!(callee
+        .to_string()
+        .starts_with("Can only call functions and classes."));
+}
+
+#[test]
+fn top_level_return_is_a_compile_error() {
+    assert!(matches!(
+        vm().interpret("return 1;"),
+        Err(LoxError::CompileError(_))
+    ));
+}
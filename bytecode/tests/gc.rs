@@ -0,0 +1,64 @@
+use std::{fs, io, path::Path};
+
+use bytecode::exec::vm::VirtualMachine;
+use shared::io::Capture;
+
+const GARBAGE: &str = "
+class Node { init(value, next) { this.value = value; this.next = next; } }
+fun makeAdder(n) { fun add(x) { return x + n; } return add; }
+
+var list = nil;
+for (var i = 0; i < 200; i = i + 1) {
+  list = Node(\"item \" + \"number\", list);
+  var dropped = Node(i, nil);
+  var adder = makeAdder(i);
+  list.value = adder(i);
+}
+
+var count = 0;
+var node = list;
+while (node != nil) { count = count + 1; node = node.next; }
+print count;
+print makeAdder(1)(2);
+print deepEqual(deepCopy(list), list);
+";
+
+/// Runs `source` and returns what it printed, followed by the error if it failed
+fn run(source: &str, stress: bool) -> String {
+    let stdout = Capture::default();
+    let mut vm = VirtualMachine::with_streams(stdout.clone(), io::sink(), io::empty());
+    vm.set_gc_stress(stress);
+
+    let result = vm.interpret(source);
+    let mut output = stdout.contents();
+    if let Err(error) = result {
+        output.push_str(&error.to_string());
+    }
+    output
+}
+
+#[test]
+fn stress_mode_keeps_live_objects() {
+    assert_eq!(run(GARBAGE, true), "200\n3\ntrue\n");
+}
+
+#[test]
+fn stress_mode_prints_the_same() {
+    let manifest = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let mut sources = vec![GARBAGE.to_string()];
+
+    for dir in [manifest.join("../lox"), manifest.join("tests/lox")] {
+        for entry in fs::read_dir(dir).expect("Failed to read script directory") {
+            let path = entry.expect("Failed to read script").path();
+
+            // while.lox never terminates
+            if path.extension().is_some_and(|ext| ext == "lox") && !path.ends_with("while.lox") {
+                sources.push(fs::read_to_string(path).expect("Failed to read script"));
+            }
+        }
+    }
+
+    for source in sources {
+        assert_eq!(run(&source, false), run(&source, true), "{source}");
+    }
+}
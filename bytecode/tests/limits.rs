@@ -0,0 +1,63 @@
+mod common;
+
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use bytecode::repr::error::{Interrupt, LoxError};
+use common::vm;
+
+fn interrupt(result: Result<(), LoxError>) -> Option<Interrupt> {
+    match result {
+        Err(LoxError::InterruptError(interrupt)) => Some(interrupt),
+        _ => None,
+    }
+}
+
+#[test]
+fn fuel_runs_out() {
+    let mut vm = vm();
+    vm.set_fuel(Some(1000));
+    assert_eq!(
+        interrupt(vm.interpret("while (true) {}")),
+        Some(Interrupt::Fuel)
+    );
+    assert_eq!(vm.fuel(), Some(0));
+
+    vm.set_fuel(Some(1000));
+    vm.interpret("var a = 1 + 2;").unwrap();
+    assert!(vm.fuel().unwrap() < 1000);
+}
+
+#[test]
+fn deadline_passes() {
+    let mut vm = vm();
+    vm.set_deadline(Some(Instant::now() + Duration::from_millis(50)));
+    assert_eq!(
+        interrupt(vm.interpret("while (true) {}")),
+        Some(Interrupt::Deadline)
+    );
+
+    vm.set_deadline(None);
+    vm.interpret("var a = 1;").unwrap();
+}
+
+#[test]
+fn handle_interrupts_from_another_thread() {
+    let mut vm = vm();
+    let handle = vm.interrupt_handle();
+
+    let interrupter = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        handle.interrupt();
+    });
+
+    assert_eq!(
+        interrupt(vm.interpret("while (true) {}")),
+        Some(Interrupt::Handle)
+    );
+    interrupter.join().unwrap();
+
+    vm.interpret("var a = 1;").unwrap();
+}
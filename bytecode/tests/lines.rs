@@ -0,0 +1,47 @@
+mod common;
+
+use bytecode::repr::{
+    chunk::{Chunk, Location},
+    error::LoxError,
+    opcode::Instruction,
+};
+use common::vm;
+
+#[test]
+fn lookup_covers_operands_and_gaps() {
+    let mut chunk = Chunk::new();
+    chunk.write(Instruction::Constant, Location::new(1, 7, 1));
+    chunk.write_byte(0);
+    chunk.write(Instruction::Constant, Location::new(1, 11, 1));
+    chunk.write_byte(1);
+    chunk.write(Instruction::Add, Location::new(1, 9, 1));
+    chunk.write(Instruction::Print, Location::new(1, 1, 5));
+    chunk.write(Instruction::Nil, Location::new(4, 1, 3));
+    chunk.write(Instruction::Return, Location::new(9, 1, 1));
+
+    let lines: Vec<usize> = (0..chunk.len()).map(|offset| chunk.line(offset)).collect();
+    assert_eq!(lines, [1, 1, 1, 1, 1, 1, 4, 9]);
+
+    // Only the operator that can fail gets a run of its own within the line
+    assert_eq!(chunk.lines().len(), 4);
+    assert_eq!(chunk.location(4), Location::new(1, 9, 1));
+}
+
+fn error_lines(source: &str) -> (Option<usize>, Vec<usize>) {
+    let Err(LoxError::RuntimeError(error)) = vm().interpret(source) else {
+        panic!("Expected a runtime error");
+    };
+    let trace = error.trace().iter().map(|frame| frame.line()).collect();
+    (error.line(), trace)
+}
+
+#[test]
+fn runtime_errors_report_their_lines() {
+    let mut source = "var a = 1;\n\n".repeat(20);
+    source.push_str("print a +\n  \"b\";\n");
+    assert_eq!(error_lines(&source), (Some(41), vec![41]));
+
+    let source =
+        "fun inner() {\n  return nil.field;\n}\n\nfun outer() {\n  inner();\n}\n\nouter();\n";
+    assert_eq!(error_lines(source), (Some(2), vec![2, 6, 9]));
+}
@@ -0,0 +1,116 @@
+mod common;
+
+use std::{fs, io, path::Path};
+
+use bytecode::{
+    exec::vm::VirtualMachine,
+    repr::{error::LoxError, native::NativeCtx, value::Value},
+};
+use common::vm;
+use shared::io::Capture;
+
+const SCRIPT: &str = "
+var greeting = \"hello\";
+fun greet(name) { return greeting + \" \" + name; }
+for (var i = 0; i < 2; i = i + 1) { print greet(\"lox\"); }
+";
+
+fn host(_ctx: &mut NativeCtx, _arguments: &[Value]) -> Result<Value, String> {
+    Ok(Value::Nil)
+}
+
+fn run(vm: &mut VirtualMachine, stdout: &Capture, bytes: &[u8]) -> String {
+    vm.interpret_bytecode(bytes).unwrap();
+    stdout.contents()
+}
+
+#[test]
+fn globals_are_relinked_on_load() {
+    let bytes = vm().compile(SCRIPT).unwrap();
+
+    let stdout = Capture::default();
+    let mut host_vm = VirtualMachine::with_streams(stdout.clone(), io::sink(), io::empty());
+    host_vm.define_native("host", 0, host);
+
+    assert_eq!(run(&mut host_vm, &stdout, &bytes), "hello lox\nhello lox\n");
+}
+
+#[test]
+fn globals_widen_past_the_short_form() {
+    let bytes = vm().compile(SCRIPT).unwrap();
+
+    let stdout = Capture::default();
+    let mut host_vm = VirtualMachine::with_streams(stdout.clone(), io::sink(), io::empty());
+    for index in 0..300 {
+        host_vm.define_native(&format!("host{index}"), 0, host);
+    }
+
+    assert_eq!(run(&mut host_vm, &stdout, &bytes), "hello lox\nhello lox\n");
+}
+
+#[test]
+fn locations_survive_serialization() {
+    let source = "var a = nil;\nprint 1 + -a;\n";
+    let bytes = vm().compile(source).unwrap();
+
+    let Err(LoxError::RuntimeError(error)) = vm().interpret_bytecode(&bytes) else {
+        panic!("Expected a runtime error");
+    };
+
+    let location = error.location().expect("Expected a trace");
+    assert_eq!((location.line(), location.column()), (2, 11));
+    assert_eq!(
+        error.to_string(),
+        "Operand must be a number\n[line 2] in script"
+    );
+}
+
+#[test]
+fn embedded_source_is_underlined() {
+    let source = "var a = nil;\nprint 1 + -a;\n";
+    let mut compiler = vm();
+    compiler.set_embed_source(true);
+    let bytes = compiler.compile(source).unwrap();
+
+    let Err(LoxError::RuntimeError(error)) = vm().interpret_bytecode(&bytes) else {
+        panic!("Expected a runtime error");
+    };
+
+    assert_eq!(
+        error.to_string(),
+        "Operand must be a number\nprint 1 + -a;\n          ^\n[line 2] in script"
+    );
+    assert!(bytes.len() > vm().compile(source).unwrap().len() + source.len());
+}
+
+fn load(name: &str) -> Result<(), LoxError> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/loxc")
+        .join(name);
+    let bytes = fs::read(path).expect("Failed to read bytecode");
+    vm().interpret_bytecode(&bytes)
+}
+
+#[test]
+fn script_upvalues_are_rejected() {
+    assert!(matches!(
+        load("script_upvalue.loxc"),
+        Err(LoxError::VerifyError(_))
+    ));
+}
+
+#[test]
+fn methods_outside_classes_are_errors() {
+    assert!(matches!(
+        load("method_outside_class.loxc"),
+        Err(LoxError::BytecodeError(_))
+    ));
+}
+
+#[test]
+fn deeply_nested_functions_are_rejected() {
+    assert!(matches!(
+        load("deep_nesting.loxc"),
+        Err(LoxError::BytecodeError(_))
+    ));
+}
@@ -0,0 +1,152 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+fn klox(args: &[&str], script: &Path) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_bytecode"))
+        .args(args)
+        .arg(script)
+        .output()
+        .expect("Failed to run klox")
+}
+
+fn scripts() -> Vec<PathBuf> {
+    let manifest = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let mut scripts = vec![];
+
+    for dir in [manifest.join("../lox"), manifest.join("tests/lox")] {
+        for entry in fs::read_dir(dir).expect("Failed to read script directory") {
+            let path = entry.expect("Failed to read script").path();
+
+            // while.lox never terminates
+            if path.extension().is_some_and(|ext| ext == "lox") && !path.ends_with("while.lox") {
+                scripts.push(path);
+            }
+        }
+    }
+
+    scripts.sort();
+    scripts
+}
+
+#[test]
+fn optimized_output_matches() {
+    for script in scripts() {
+        let plain = klox(&[], &script);
+        let optimized = klox(&["-O"], &script);
+
+        assert_eq!(plain.status.code(), optimized.status.code(), "{script:?}");
+        assert_eq!(plain.stdout, optimized.stdout, "{script:?}");
+        assert_eq!(plain.stderr, optimized.stderr, "{script:?}");
+    }
+}
+
+#[test]
+fn optimized_bytecode_matches() {
+    let dir = std::env::temp_dir();
+
+    for script in scripts() {
+        let name = script.file_stem().expect("Script without a name");
+        let output = dir.join(name).with_extension("loxc");
+        let output = output.to_str().expect("Non UTF-8 temp path");
+
+        let compiled = Command::new(env!("CARGO_BIN_EXE_bytecode"))
+            .args(["-O", "compile", "--embed-source"])
+            .arg(&script)
+            .args(["-o", output])
+            .status()
+            .expect("Failed to run klox");
+        assert!(compiled.success(), "{script:?}");
+
+        let plain = klox(&[], &script);
+        let optimized = klox(&[], Path::new(output));
+
+        assert_eq!(plain.stdout, optimized.stdout, "{script:?}");
+        assert_eq!(plain.stderr, optimized.stderr, "{script:?}");
+    }
+}
+
+#[test]
+fn comparisons_are_fused() {
+    let script = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/lox/peephole.lox");
+
+    let plain = String::from_utf8(klox(&["--disassemble"], &script).stdout).unwrap();
+    let optimized = String::from_utf8(klox(&["-O", "--disassemble"], &script).stdout).unwrap();
+
+    assert!(!plain.contains("NotEqual"));
+    for fused in ["NotEqual", "GreaterEqual", "LessEqual", "PopN"] {
+        assert!(optimized.contains(fused), "missing {fused}");
+    }
+    assert!(optimized.lines().count() < plain.lines().count());
+}
+
+#[test]
+fn constants_are_folded() {
+    let script = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/lox/folding.lox");
+
+    let plain = String::from_utf8(klox(&["--disassemble"], &script).stdout).unwrap();
+    let optimized = String::from_utf8(klox(&["-O", "--disassemble"], &script).stdout).unwrap();
+
+    assert!(plain.contains("'dead'"));
+    assert!(!optimized.contains("'dead'"));
+    assert!(optimized.contains("'foobarbaz'"));
+    assert!(optimized.lines().count() < plain.lines().count());
+}
+
+fn disassemble(optimize: bool, source: &str) -> String {
+    let mut vm = bytecode::exec::vm::VirtualMachine::new();
+    vm.set_optimize(optimize);
+
+    let mut out = vec![];
+    vm.disassemble(source, &mut out)
+        .expect("Failed to disassemble");
+    String::from_utf8(out).expect("Non UTF-8 disassembly")
+}
+
+#[test]
+fn peephole_rewrites_apply() {
+    let source = "
+fun f(a, b) {
+  if (!(a < b)) print a != b;
+  { var x = a + b; var y = a * b; }
+  a;
+}
+";
+    let plain = disassemble(false, source);
+    let optimized = disassemble(true, source);
+
+    for fused in ["GreaterEqual", "NotEqual", "PopN"] {
+        assert!(
+            optimized.contains(fused),
+            "{fused} missing from\n{optimized}"
+        );
+    }
+    assert!(
+        !optimized.lines().any(|line| line.ends_with(" Not")),
+        "{optimized}"
+    );
+    assert!(optimized.lines().count() < plain.lines().count());
+}
+
+#[test]
+fn folding_leaves_no_dead_constants() {
+    let source = "
+print \"a\" + \"b\";
+if (false) { fun f() { print \"dead\"; } }
+print -(1 + 2);
+var x = true or \"never\";
+print \"last\";
+";
+    let optimized = disassemble(true, source);
+
+    let constants: Vec<&str> = optimized
+        .lines()
+        .filter(|line| line.contains(" Constant "))
+        .filter_map(|line| line.split_whitespace().nth(3))
+        .collect();
+    assert_eq!(constants, ["0", "1", "2"], "{optimized}");
+    assert!(optimized.contains("'ab'") && optimized.contains("'-3'"));
+    assert!(!optimized.contains("<fn f>"), "{optimized}");
+}
@@ -0,0 +1,37 @@
+use std::io;
+
+use bytecode::{exec::vm::VirtualMachine, repl};
+use shared::io::Capture;
+
+#[test]
+fn print_goes_to_stdout_sink() {
+    let stdout = Capture::default();
+    let stderr = Capture::default();
+    let mut vm = VirtualMachine::with_streams(stdout.clone(), stderr.clone(), io::empty());
+
+    vm.interpret("print 1 + 2;\nprint \"lox\";").unwrap();
+    assert!(vm.interpret("print nope;").is_err());
+
+    assert_eq!(stdout.contents(), "3\nlox\n");
+    assert_eq!(stderr.contents(), "");
+}
+
+#[test]
+fn stdin_reads_from_source() {
+    let mut vm = VirtualMachine::with_streams(io::sink(), io::sink(), &b"first\nsecond\n"[..]);
+
+    let mut line = String::new();
+    vm.stdin().read_line(&mut line).unwrap();
+    assert_eq!(line, "first\n");
+}
+
+#[test]
+fn repl_writes_to_stdout_sink() {
+    let stdout = Capture::default();
+    let vm = VirtualMachine::with_streams(stdout.clone(), io::sink(), &b"print 1;\n"[..]);
+
+    repl(vm).unwrap();
+
+    let version = env!("CARGO_PKG_VERSION");
+    assert_eq!(stdout.contents(), format!("klox v{version}\n> 1\n> "));
+}
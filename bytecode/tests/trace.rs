@@ -0,0 +1,91 @@
+use std::{
+    fs, io,
+    ops::RangeInclusive,
+    process::{Command, Output},
+};
+
+use bytecode::exec::vm::VirtualMachine;
+use shared::io::Capture;
+
+const SOURCE: &str = "var a = 1;\nvar b = a + 2;\nprint b;\n";
+
+fn traced(lines: Option<RangeInclusive<usize>>) -> (String, String) {
+    let stdout = Capture::default();
+    let trace = Capture::default();
+
+    let mut vm = VirtualMachine::with_streams(stdout.clone(), io::sink(), io::empty());
+    vm.set_trace(trace.clone());
+    vm.set_trace_lines(lines);
+    vm.interpret(SOURCE).unwrap();
+
+    (stdout.contents(), trace.contents())
+}
+
+#[test]
+fn trace_shows_the_stack_before_each_instruction() {
+    let (stdout, trace) = traced(None);
+    assert_eq!(stdout, "3\n");
+
+    let trace: Vec<&str> = trace.lines().collect();
+    assert_eq!(trace.len(), 2 * 10);
+    assert_eq!(trace[0], "          [ <script> ]");
+    assert_eq!(trace[1], "0000    1 Constant            0 '1'");
+    assert_eq!(trace[8], "          [ <script> ][ 1 ][ 2 ]");
+    assert_eq!(trace[9], "0008    | Add");
+    assert_eq!(trace[19], "0015    | Return");
+}
+
+#[test]
+fn trace_lines_filter_instructions() {
+    let (stdout, trace) = traced(Some(2..=2));
+    assert_eq!(stdout, "3\n");
+
+    let instructions: Vec<&str> = trace.lines().skip(1).step_by(2).collect();
+    assert_eq!(
+        instructions,
+        [
+            "0004    2 GetGlobal           4 'a'",
+            "0006    | Constant            1 '2'",
+            "0008    | Add",
+            "0009    | DefineGlobal        5 'b'",
+        ]
+    );
+
+    let (_, trace) = traced(Some(5..=9));
+    assert_eq!(trace, "");
+}
+
+#[test]
+fn clearing_the_trace_stops_it() {
+    let trace = Capture::default();
+    let mut vm = VirtualMachine::with_streams(io::sink(), io::sink(), io::empty());
+    vm.set_trace(trace.clone());
+    vm.clear_trace();
+    vm.interpret(SOURCE).unwrap();
+
+    assert_eq!(trace.contents(), "");
+}
+
+fn klox(args: &[&str]) -> Output {
+    let script = std::env::temp_dir().join("klox_trace.lox");
+    fs::write(&script, SOURCE).expect("Failed to write script");
+
+    Command::new(env!("CARGO_BIN_EXE_bytecode"))
+        .args(args)
+        .arg(&script)
+        .output()
+        .expect("Failed to run klox")
+}
+
+#[test]
+fn trace_flag_writes_to_stderr() {
+    let output = klox(&["--trace=3-3"]);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "3\n");
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("0011    3 GetGlobal"), "{stderr}");
+    assert!(!stderr.contains("Constant"), "{stderr}");
+
+    assert_eq!(klox(&["--trace=x"]).status.code(), Some(64));
+}
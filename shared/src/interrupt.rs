@@ -0,0 +1,132 @@
+use std::{
+    fmt::Display,
+    process,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
+
+const INTERRUPT_INTERVAL: u64 = 1024;
+
+/// Why a script was stopped before it finished
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interrupt {
+    Fuel,
+    Deadline,
+    Handle,
+}
+
+impl Display for Interrupt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Interrupt::Fuel => write!(f, "Ran out of fuel."),
+            Interrupt::Deadline => write!(f, "Deadline exceeded."),
+            Interrupt::Handle => write!(f, "Interrupted."),
+        }
+    }
+}
+
+/// Stops a running script from another thread
+#[derive(Debug, Clone)]
+pub struct InterruptHandle {
+    interrupted: Arc<AtomicBool>,
+    running: Arc<AtomicBool>,
+}
+
+impl InterruptHandle {
+    /// Returns false, without interrupting anything, when no script is running
+    pub fn interrupt(&self) -> bool {
+        if !self.running.load(Ordering::Relaxed) {
+            return false;
+        }
+
+        self.interrupted.store(true, Ordering::Relaxed);
+        true
+    }
+}
+
+/// The fuel, deadline and interrupt state an engine checks as it runs
+#[derive(Debug, Default)]
+pub struct Limits {
+    fuel: Option<u64>,
+    deadline: Option<Instant>,
+    ticks: u64,
+    interrupted: Arc<AtomicBool>,
+    running: Arc<AtomicBool>,
+}
+
+impl Limits {
+    pub fn new() -> Self {
+        Limits::default()
+    }
+
+    pub fn set_fuel(&mut self, fuel: Option<u64>) {
+        self.fuel = fuel;
+    }
+
+    pub fn fuel(&self) -> Option<u64> {
+        self.fuel
+    }
+
+    pub fn set_deadline(&mut self, deadline: Option<Instant>) {
+        self.deadline = deadline;
+    }
+
+    pub fn handle(&self) -> InterruptHandle {
+        InterruptHandle {
+            interrupted: Arc::clone(&self.interrupted),
+            running: Arc::clone(&self.running),
+        }
+    }
+
+    /// Marks a script as running, forgetting any interrupt that arrived before it
+    pub fn start(&self) {
+        self.interrupted.store(false, Ordering::Relaxed);
+        self.running.store(true, Ordering::Relaxed);
+    }
+
+    pub fn stop(&self) {
+        self.running.store(false, Ordering::Relaxed);
+    }
+
+    /// Spends one unit of fuel and checks whether the script should stop
+    #[inline]
+    pub fn tick(&mut self) -> Result<(), Interrupt> {
+        if let Some(fuel) = &mut self.fuel {
+            if *fuel == 0 {
+                return Err(Interrupt::Fuel);
+            }
+            *fuel -= 1;
+        }
+
+        // Checking the clock is expensive, so only do it every so often
+        self.ticks += 1;
+        if self.ticks.is_multiple_of(INTERRUPT_INTERVAL) {
+            if self.interrupted.swap(false, Ordering::Relaxed) {
+                return Err(Interrupt::Handle);
+            }
+
+            if self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+            {
+                return Err(Interrupt::Deadline);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Lets Ctrl-C stop the running script, or exit the process when nothing is running
+pub fn interrupt_on_ctrlc(handle: InterruptHandle) {
+    if let Err(error) = ctrlc::set_handler(move || {
+        if !handle.interrupt() {
+            process::exit(130);
+        }
+    }) {
+        eprintln!("Failed to set the Ctrl-C handler: {error}");
+    }
+}
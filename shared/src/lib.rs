@@ -0,0 +1,4 @@
+pub mod interrupt;
+pub mod io;
+pub mod span;
+pub mod value;
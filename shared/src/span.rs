@@ -0,0 +1,22 @@
+/// Renders the source line containing the byte range `start..end`, with carets under the range
+pub fn underline(source: &str, start: usize, end: usize) -> String {
+    let start = start.min(source.len());
+    let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = source[start..]
+        .find('\n')
+        .map_or(source.len(), |i| start + i);
+    let end = end.clamp(start, line_end);
+
+    let padding: String = source[line_start..start]
+        .chars()
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    let width = source[start..end].chars().count().max(1);
+
+    format!(
+        "{}\n{}{}",
+        &source[line_start..line_end],
+        padding,
+        "^".repeat(width)
+    )
+}
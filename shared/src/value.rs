@@ -0,0 +1,133 @@
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hash,
+};
+
+/// What `==` looks at in a value
+#[derive(Debug, Clone, Copy)]
+pub enum Operand<'a, O> {
+    Nil,
+    Boolean(bool),
+    Number(f64),
+    String(&'a str),
+    Object(O),
+}
+
+/// The equality rules of `==`: primitives by value, objects by identity
+pub fn equal<O: PartialEq>(a: Operand<O>, b: Operand<O>) -> bool {
+    use Operand::*;
+
+    match (a, b) {
+        (Nil, Nil) => true,
+        (Boolean(a), Boolean(b)) => a == b,
+        (Number(a), Number(b)) => a == b,
+        (String(a), String(b)) => a == b,
+        (Object(a), Object(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// An engine's values, as seen by `deepEqual`, `freeze` and `deepCopy`.
+/// Nodes are the aggregates those natives look inside; everything else is a leaf
+pub trait Graph {
+    type Value: Clone;
+    type Node: Copy + Eq + Hash;
+    type Key: Copy;
+
+    /// `==` on two values
+    fn equal(&self, a: &Self::Value, b: &Self::Value) -> bool;
+
+    fn node(&self, value: &Self::Value) -> Option<Self::Node>;
+    fn value(&self, node: Self::Node) -> Self::Value;
+
+    /// Whether two nodes can be equal before their fields are compared
+    fn same_shape(&self, a: Self::Node, b: Self::Node) -> bool;
+    fn fields(&self, node: Self::Node) -> Vec<(Self::Key, Self::Value)>;
+    fn field(&self, node: Self::Node, key: Self::Key) -> Option<Self::Value>;
+
+    /// Marks the node read-only, returning false if it already was
+    fn freeze(&mut self, node: Self::Node) -> bool;
+    /// A new node of the same shape with no fields
+    fn alloc_like(&mut self, node: Self::Node) -> Self::Node;
+    fn set_field(&mut self, node: Self::Node, key: Self::Key, value: Self::Value);
+}
+
+/// Compares values field by field. A pair of nodes already being compared is assumed equal,
+/// so cycles terminate
+pub fn deep_equal<G: Graph>(graph: &G, a: &G::Value, b: &G::Value) -> bool {
+    let mut pending = vec![(a.clone(), b.clone())];
+    let mut visited = HashSet::new();
+
+    while let Some((a, b)) = pending.pop() {
+        let (Some(a), Some(b)) = (graph.node(&a), graph.node(&b)) else {
+            if !graph.equal(&a, &b) {
+                return false;
+            }
+            continue;
+        };
+
+        if a == b || !visited.insert((a, b)) {
+            continue;
+        }
+        if !graph.same_shape(a, b) {
+            return false;
+        }
+
+        for (key, value) in graph.fields(a) {
+            let Some(other) = graph.field(b, key) else {
+                return false;
+            };
+            pending.push((value, other));
+        }
+    }
+
+    true
+}
+
+/// Freezes every node reachable from the value
+pub fn freeze<G: Graph>(graph: &mut G, value: &G::Value) {
+    let mut pending = vec![value.clone()];
+
+    while let Some(value) = pending.pop() {
+        if let Some(node) = graph.node(&value) {
+            if graph.freeze(node) {
+                pending.extend(graph.fields(node).into_iter().map(|(_, field)| field));
+            }
+        }
+    }
+}
+
+/// Copies every node reachable from the value, preserving sharing and cycles
+pub fn deep_copy<G: Graph>(graph: &mut G, value: &G::Value) -> G::Value {
+    let Some(root) = graph.node(value) else {
+        return value.clone();
+    };
+
+    let mut copies = HashMap::from([(root, graph.alloc_like(root))]);
+    let mut pending = vec![root];
+
+    while let Some(original) = pending.pop() {
+        let copy = copies[&original];
+
+        for (key, field) in graph.fields(original) {
+            let field = match graph.node(&field) {
+                Some(node) => {
+                    let copied = match copies.get(&node) {
+                        Some(&copied) => copied,
+                        None => {
+                            let copied = graph.alloc_like(node);
+                            copies.insert(node, copied);
+                            pending.push(node);
+                            copied
+                        }
+                    };
+                    graph.value(copied)
+                }
+                None => field,
+            };
+            graph.set_field(copy, key, field);
+        }
+    }
+
+    graph.value(copies[&root])
+}
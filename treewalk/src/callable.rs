@@ -2,7 +2,7 @@ use std::fmt::Display;
 
 use crate::{
     error::LoxError,
-    function::{Clock, Function},
+    function::{Clock, Function, Native},
     interpreter::Interpreter,
     value::Value,
 };
@@ -11,6 +11,7 @@ use crate::{
 pub enum Callable {
     Clock(Clock),
     Function(Function),
+    Native(Native),
 }
 
 impl Callable {
@@ -22,6 +23,7 @@ impl Callable {
         match self {
             Callable::Function(function) => function.call(interpreter, arguments),
             Callable::Clock(clock) => clock.call(interpreter, arguments),
+            Callable::Native(native) => native.call(interpreter, arguments),
         }
     }
 
@@ -29,6 +31,18 @@ impl Callable {
         match self {
             Callable::Clock(_) => 0,
             Callable::Function(function) => function.arity(),
+            Callable::Native(native) => native.arity(),
+        }
+    }
+}
+
+impl PartialEq for Callable {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Callable::Clock(_), Callable::Clock(_)) => true,
+            (Callable::Function(left), Callable::Function(right)) => left == right,
+            (Callable::Native(left), Callable::Native(right)) => left == right,
+            _ => false,
         }
     }
 }
@@ -38,6 +52,7 @@ impl Display for Callable {
         let display = match self {
             Callable::Clock(clock) => clock.to_string(),
             Callable::Function(function) => function.to_string(),
+            Callable::Native(native) => native.to_string(),
         };
 
         write!(f, "{}", display)
@@ -41,20 +41,19 @@ impl Environment {
     }
 
     pub fn get(&self, name: &Token) -> Result<Value, LoxError> {
-        let data = self.data.borrow();
+        self.lookup(&name.lexeme()).ok_or_else(|| {
+            LoxError::runtime(name, format!("Undefined variable '{}'.", name.lexeme()))
+        })
+    }
 
-        if let Some(value) = data.values.get(&name.lexeme()) {
-            return Ok(value.clone());
-        }
+    pub fn lookup(&self, name: &str) -> Option<Value> {
+        let data = self.data.borrow();
 
-        if let Some(enclosing) = &data.enclosing {
-            return enclosing.get(name);
+        if let Some(value) = data.values.get(name) {
+            return Some(value.clone());
         }
 
-        Err(LoxError::runtime(
-            name,
-            format!("Undefined variable '{}'.", name.lexeme()),
-        ))
+        data.enclosing.as_ref()?.lookup(name)
     }
 
     pub fn assign(&self, name: &Token, value: Value) -> Result<(), LoxError> {
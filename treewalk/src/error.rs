@@ -1,7 +1,9 @@
 use std::fmt::Display;
 
+use shared::interrupt::Interrupt;
+
 use crate::{
-    token::{Token, TokenType},
+    token::{Span, Token, TokenType},
     value::Value,
 };
 
@@ -10,44 +12,58 @@ pub enum LoxErrorType {
     SyntaxError,
     RuntimeError,
     Return(Value),
+    Interrupted(Interrupt),
 }
 
 impl Display for LoxErrorType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?}", self)
+        match self {
+            LoxErrorType::Interrupted(_) => write!(f, "Stopped"),
+            _ => write!(f, "{:?}", self),
+        }
     }
 }
 
 #[derive(Debug)]
 pub struct LoxError {
-    line: i32,
+    span: Span,
     message: String,
     at: String,
     kind: LoxErrorType,
 }
 
 impl LoxError {
-    pub fn new<S: Into<String>>(line: i32, message: S, kind: LoxErrorType) -> Self {
-        LoxError::at(line, "", &message.into(), kind)
+    pub fn new<S: Into<String>>(span: Span, message: S, kind: LoxErrorType) -> Self {
+        LoxError::at(span, "", &message.into(), kind)
     }
 
-    pub fn at<S: Into<String>>(line: i32, at: S, message: S, kind: LoxErrorType) -> Self {
-        let error = LoxError {
-            line,
+    pub fn at<S: Into<String>>(span: Span, at: S, message: S, kind: LoxErrorType) -> Self {
+        LoxError {
+            span,
             message: message.into(),
             at: at.into(),
             kind,
-        };
-        eprintln!("{error}");
-        error
+        }
     }
 
     pub fn kind(&self) -> &LoxErrorType {
         &self.kind
     }
 
-    pub fn error<S: Into<String>>(line: i32, message: S, kind: LoxErrorType) -> LoxError {
-        LoxError::report(line, "", &message.into(), kind)
+    pub fn line(&self) -> i32 {
+        self.span.line()
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn render(&self, source: &str) -> String {
+        format!("{}\n{}", self, self.span.underline(source))
+    }
+
+    pub fn error<S: Into<String>>(span: Span, message: S, kind: LoxErrorType) -> LoxError {
+        LoxError::report(span, "", &message.into(), kind)
     }
 
     pub fn syntax<S: Into<String>>(token: &Token, message: S) -> LoxError {
@@ -57,12 +73,12 @@ impl LoxError {
             format!(" at '{}'", token.lexeme())
         };
 
-        LoxError::report(token.line(), at, message.into(), LoxErrorType::SyntaxError)
+        LoxError::report(token.span(), at, message.into(), LoxErrorType::SyntaxError)
     }
 
     pub fn runtime<S: Into<String>>(token: &Token, message: S) -> LoxError {
         LoxError::at(
-            token.line(),
+            token.span(),
             "",
             &message.into(),
             LoxErrorType::RuntimeError,
@@ -71,15 +87,15 @@ impl LoxError {
 
     pub fn return_value(token: Token, value: Value) -> LoxError {
         LoxError {
-            line: token.line(),
+            span: token.span(),
             kind: LoxErrorType::Return(value.clone()),
             message: format!("return {}", value),
             at: String::new(),
         }
     }
 
-    fn report<S: Into<String>>(line: i32, at: S, message: S, kind: LoxErrorType) -> LoxError {
-        LoxError::at(line, at, message, kind)
+    fn report<S: Into<String>>(span: Span, at: S, message: S, kind: LoxErrorType) -> LoxError {
+        LoxError::at(span, at, message, kind)
     }
 }
 
@@ -88,7 +104,10 @@ impl Display for LoxError {
         write!(
             f,
             "[line {}] {}{}: {}",
-            self.line, self.kind, self.at, self.message
+            self.span.line(),
+            self.kind,
+            self.at,
+            self.message
         )
     }
 }
@@ -4,7 +4,7 @@ use uuid::Uuid;
 
 use crate::{
     operator::{BinOp, LogOp, UnOp},
-    token::Token,
+    token::{Span, Token},
     value::Value,
 };
 
@@ -24,12 +24,13 @@ pub enum ExprType {
 #[derive(Debug, Clone)]
 pub struct Expr {
     id: Uuid,
-    kind: ExprType
+    kind: ExprType,
+    span: Span,
 }
 
 impl Expr {
     pub fn new() -> Self {
-        Expr::create(ExprType::Empty)
+        Expr::create(ExprType::Empty, Span::default())
     }
 
     pub fn id(&self) -> Uuid {
@@ -40,50 +41,61 @@ impl Expr {
         &self.kind
     }
 
-    pub fn grouping(expr: Expr) -> Expr {
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn grouping(expr: Expr, span: Span) -> Expr {
         let kind = ExprType::Grouping(Box::new(expr));
-        Expr::create(kind)
+        Expr::create(kind, span)
     }
 
     pub fn binary(operator: BinOp, left: Expr, right: Expr) -> Expr {
+        let span = left.span.to(right.span);
         let kind = ExprType::Binary(operator, Box::new(left), Box::new(right));
-        Expr::create(kind)
+        Expr::create(kind, span)
     }
 
     pub fn unary(operator: UnOp, right: Expr) -> Expr {
+        let span = operator.token().span().to(right.span);
         let kind = ExprType::Unary(operator, Box::new(right));
-        Expr::create(kind)
+        Expr::create(kind, span)
     }
 
     pub fn logical(operator: LogOp, left: Expr, right: Expr) -> Expr {
+        let span = left.span.to(right.span);
         let kind = ExprType::Logical(operator, Box::new(left), Box::new(right));
-        Expr::create(kind)
+        Expr::create(kind, span)
     }
 
-    pub fn literal(value: Value) -> Expr {
+    pub fn literal(value: Value, span: Span) -> Expr {
         let kind = ExprType::Literal(value);
-        Expr::create(kind)
+        Expr::create(kind, span)
     }
 
     pub fn variable(name: Token) -> Expr {
+        let span = name.span();
         let kind = ExprType::Variable(name);
-        Expr::create(kind)
+        Expr::create(kind, span)
     }
 
     pub fn assign(name: Token, expr: Expr) -> Expr {
+        let span = name.span().to(expr.span);
         let kind = ExprType::Assign(name, Box::new(expr));
-        Expr::create(kind)
+        Expr::create(kind, span)
     }
 
     pub fn call(callee: Expr, paren: Token, arguments: Vec<Expr>) -> Expr {
+        let span = callee.span.to(paren.span());
         let kind = ExprType::Call(Box::new(callee), paren, arguments);
-        Expr::create(kind)
+        Expr::create(kind, span)
     }
 
-    fn create(kind: ExprType) -> Self {
+    fn create(kind: ExprType, span: Span) -> Self {
         Expr {
             id: Uuid::new_v4(),
-            kind
+            kind,
+            span,
         }
     }
 }
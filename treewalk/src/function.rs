@@ -3,6 +3,9 @@ use std::{
     time::{SystemTime, UNIX_EPOCH},
 };
 
+use shared::value;
+use uuid::Uuid;
+
 use crate::{
     callable::Callable,
     environment::Environment,
@@ -10,24 +13,24 @@ use crate::{
     interpreter::Interpreter,
     stmt::Stmt,
     token::Token,
-    value::Value,
+    value::{Value, Values},
 };
 
 #[derive(Debug, Clone)]
 pub struct Function {
+    id: Uuid,
     name: Token,
     params: Vec<Token>,
     body: Vec<Stmt>,
-    closure: Environment,
 }
 
 impl Function {
-    pub fn new(name: Token, params: Vec<Token>, body: Vec<Stmt>, closure: Environment) -> Self {
+    pub fn new(name: Token, params: Vec<Token>, body: Vec<Stmt>) -> Self {
         Function {
+            id: Uuid::new_v4(),
             name,
             params,
             body,
-            closure,
         }
     }
 
@@ -67,6 +70,12 @@ impl Function {
     }
 }
 
+impl PartialEq for Function {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
 impl Display for Function {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "<fn {}>", self.name.lexeme())
@@ -105,3 +114,65 @@ impl Display for Clock {
         write!(f, "<native fn>")
     }
 }
+
+#[derive(Debug, Clone)]
+pub struct Native {
+    name: &'static str,
+    arity: usize,
+    function: fn(Vec<Value>) -> Value,
+}
+
+impl Native {
+    pub fn new(name: &'static str, arity: usize, function: fn(Vec<Value>) -> Value) -> Self {
+        Native {
+            name,
+            arity,
+            function,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        self.name
+    }
+
+    pub fn arity(&self) -> usize {
+        self.arity
+    }
+
+    pub fn call(
+        &mut self,
+        _interpreter: &mut Interpreter,
+        arguments: Vec<Value>,
+    ) -> Result<Value, LoxError> {
+        Ok((self.function)(arguments))
+    }
+
+    pub fn value(self) -> Value {
+        Value::Callable(Box::new(Callable::Native(self)))
+    }
+
+    pub fn freeze(mut arguments: Vec<Value>) -> Value {
+        value::freeze(&mut Values, &arguments[0]);
+        arguments.remove(0)
+    }
+
+    pub fn deep_copy(arguments: Vec<Value>) -> Value {
+        value::deep_copy(&mut Values, &arguments[0])
+    }
+
+    pub fn deep_equal(arguments: Vec<Value>) -> Value {
+        Value::Bool(value::deep_equal(&Values, &arguments[0], &arguments[1]))
+    }
+}
+
+impl PartialEq for Native {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+impl Display for Native {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<native fn>")
+    }
+}
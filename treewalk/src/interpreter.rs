@@ -1,28 +1,70 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    time::Instant,
+};
+
+use shared::interrupt::{Interrupt, InterruptHandle, Limits};
+
 use crate::{
+    convert::{Callee, FromValue, IntoArgs, IntoValue},
     environment::Environment,
-    error::LoxError,
+    error::{LoxError, LoxErrorType},
     expr::{Expr, ExprType},
-    function::{Clock, Function},
+    function::{Clock, Function, Native},
     operator::{BinOpType, LogOpType, UnOpType},
-    stmt::Stmt,
+    stmt::{Stmt, StmtType},
+    token::Span,
     value::Value,
 };
 
-#[derive(Default)]
 pub struct Interpreter {
     env: Environment,
-    globals: Environment,
+
+    stdout: Box<dyn Write>,
+    stderr: Box<dyn Write>,
+    stdin: Box<dyn BufRead>,
+
+    steps: u64,
+    limits: Limits,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Interpreter::new()
+    }
 }
 
 impl Interpreter {
     pub fn new() -> Self {
+        Interpreter::with_streams(
+            std::io::stdout(),
+            std::io::stderr(),
+            BufReader::new(std::io::stdin()),
+        )
+    }
+
+    pub fn with_streams<O, E, I>(stdout: O, stderr: E, stdin: I) -> Self
+    where
+        O: Write + 'static,
+        E: Write + 'static,
+        I: BufRead + 'static,
+    {
         let globals = Environment::new();
         let environment = Environment::new_enclosed(&globals);
         globals.define("clock", Clock::new().value());
+        globals.define("freeze", Native::new("freeze", 1, Native::freeze).value());
+        globals.define("deepCopy", Native::new("deepCopy", 1, Native::deep_copy).value());
+        globals.define("deepEqual", Native::new("deepEqual", 2, Native::deep_equal).value());
 
         Interpreter {
             env: environment,
-            globals,
+
+            stdout: Box::new(stdout),
+            stderr: Box::new(stderr),
+            stdin: Box::new(stdin),
+
+            steps: 0,
+            limits: Limits::new(),
         }
     }
 
@@ -30,41 +72,161 @@ impl Interpreter {
         &self.env
     }
 
-    pub fn interpret(&mut self, statements: Vec<Stmt>) {
-        let environment = self.env.clone();
+    pub fn stdout(&mut self) -> &mut dyn Write {
+        &mut self.stdout
+    }
 
-        for stmt in statements {
-            if self.execute(stmt, &environment).is_err() {
-                break;
-            }
+    pub fn stderr(&mut self) -> &mut dyn Write {
+        &mut self.stderr
+    }
+
+    pub fn stdin(&mut self) -> &mut dyn BufRead {
+        &mut self.stdin
+    }
+
+    /// Statements and expressions evaluated since the interpreter was created
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// Limits how many more statements and expressions may be evaluated, across every script
+    pub fn set_fuel(&mut self, fuel: Option<u64>) {
+        self.limits.set_fuel(fuel);
+    }
+
+    pub fn fuel(&self) -> Option<u64> {
+        self.limits.fuel()
+    }
+
+    pub fn set_deadline(&mut self, deadline: Option<Instant>) {
+        self.limits.set_deadline(deadline);
+    }
+
+    /// Interrupting only affects a script that is already running
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.limits.handle()
+    }
+
+    pub fn get_global<T: FromValue>(&self, name: &str) -> Result<T, LoxError> {
+        match self.env.lookup(name) {
+            Some(value) => Interpreter::convert(value),
+            None => Err(Interpreter::host_error(format!(
+                "Undefined variable '{}'.",
+                name
+            ))),
         }
     }
 
+    pub fn set_global<T: IntoValue>(&mut self, name: &str, value: T) {
+        self.env.define(name, value.into_value());
+    }
+
+    /// Calls a function by the name of a global or by value and converts what it returns
+    pub fn call<'a, C, A, R>(&mut self, callee: C, arguments: A) -> Result<R, LoxError>
+    where
+        C: Into<Callee<'a>>,
+        A: IntoArgs,
+        R: FromValue,
+    {
+        let callee = match callee.into() {
+            Callee::Global(name) => self.get_global(name)?,
+            Callee::Value(value) => value,
+        };
+
+        let Value::Callable(mut function) = callee else {
+            return Err(Interpreter::host_error(
+                "Can only call functions and classes.",
+            ));
+        };
+
+        let arguments = arguments.into_args();
+        if arguments.len() != function.arity() {
+            return Err(Interpreter::host_error(format!(
+                "Expected {} arguments but got {}.",
+                function.arity(),
+                arguments.len()
+            )));
+        }
+
+        self.limits.start();
+        let result = function.call(self, arguments);
+        self.limits.stop();
+
+        Interpreter::convert(result?)
+    }
+
+    fn convert<T: FromValue>(value: Value) -> Result<T, LoxError> {
+        match T::from_value(value.clone()) {
+            Some(converted) => Ok(converted),
+            None => Err(Interpreter::host_error(format!(
+                "Expected {} but got {}.",
+                T::EXPECTED,
+                Interpreter::output(value)
+            ))),
+        }
+    }
+
+    fn step(&mut self, span: Span) -> Result<(), LoxError> {
+        self.steps += 1;
+        self.limits
+            .tick()
+            .map_err(|interrupt| Interpreter::interrupt(span, interrupt))
+    }
+
+    fn interrupt(span: Span, interrupt: Interrupt) -> LoxError {
+        LoxError::new(
+            span,
+            interrupt.to_string(),
+            LoxErrorType::Interrupted(interrupt),
+        )
+    }
+
+    fn host_error<S: Into<String>>(message: S) -> LoxError {
+        LoxError::new(Span::default(), message, LoxErrorType::RuntimeError)
+    }
+
+    pub fn interpret(&mut self, statements: Vec<Stmt>) -> Result<(), LoxError> {
+        let environment = self.env.clone();
+
+        self.limits.start();
+        let result = statements
+            .into_iter()
+            .try_for_each(|stmt| self.execute(stmt, &environment));
+        self.limits.stop();
+
+        result
+    }
+
     fn execute(&mut self, stmt: Stmt, environment: &Environment) -> Result<(), LoxError> {
-        match stmt {
-            Stmt::Expr(expr) => {
+        let span = stmt.span();
+        self.step(span)?;
+
+        match stmt.into_kind() {
+            StmtType::Expr(expr) => {
                 self.evaluate(&expr, environment)?;
             }
-            Stmt::Print(expr) => {
+            StmtType::Print(expr) => {
                 let value = self.evaluate(&expr, environment)?;
                 let output = Interpreter::output(value);
-                println!("{}", output)
+                writeln!(self.stdout, "{}", output).map_err(|error| {
+                    LoxError::new(span, error.to_string(), LoxErrorType::RuntimeError)
+                })?;
             }
-            Stmt::Var(name, initializer) => {
+            StmtType::Var(name, initializer) => {
                 let value = self.evaluate(&initializer, environment)?;
                 self.env.define(name.lexeme(), value);
             }
 
-            Stmt::Block(statements) => {
+            StmtType::Block(statements) => {
                 self.execute_block(statements, &Environment::new_enclosed(environment))?;
             }
 
-            Stmt::If(condition, then_branch) => {
+            StmtType::If(condition, then_branch) => {
                 if Interpreter::is_truthy(&self.evaluate(&condition, environment)?) {
                     self.execute(*then_branch, environment)?;
                 }
             }
-            Stmt::IfElse(condition, then_branch, else_branch) => {
+            StmtType::IfElse(condition, then_branch, else_branch) => {
                 if Interpreter::is_truthy(&self.evaluate(&condition, environment)?) {
                     self.execute(*then_branch, environment)?;
                 } else {
@@ -72,26 +234,26 @@ impl Interpreter {
                 }
             }
 
-            Stmt::While(condition, body) => {
+            StmtType::While(condition, body) => {
                 while Interpreter::is_truthy(&self.evaluate(&condition, environment)?) {
                     self.execute(*body.clone(), environment)?;
                 }
             }
 
-            Stmt::Function(name, params, body) => {
+            StmtType::Function(name, params, body) => {
                 let display_name = name.lexeme();
 
                 //TODO: Investigate this further
-                let function = Function::new(name, params, body, Environment::new());
+                let function = Function::new(name, params, body);
                 environment.define(display_name, function.value());
             }
 
-            Stmt::Return(keyword, expr) => {
+            StmtType::Return(keyword, expr) => {
                 let value = self.evaluate(&expr, environment)?;
                 return Err(LoxError::return_value(keyword, value));
             }
 
-            Stmt::Empty => (),
+            StmtType::Empty => (),
         }
 
         Ok(())
@@ -121,6 +283,8 @@ impl Interpreter {
     }
 
     fn evaluate(&mut self, expr: &Expr, environment: &Environment) -> Result<Value, LoxError> {
+        self.step(expr.span())?;
+
         let value = match expr.kind() {
             ExprType::Empty => Value::Nil,
 
@@ -217,12 +381,8 @@ impl Interpreter {
                     }
 
                     // Equality
-                    (BinOpType::Equal, left, right) => {
-                        Value::Bool(Interpreter::is_equal(left, right))
-                    }
-                    (BinOpType::NotEqual, left, right) => {
-                        Value::Bool(!Interpreter::is_equal(left, right))
-                    }
+                    (BinOpType::Equal, left, right) => Value::Bool(left.is_equal(&right)),
+                    (BinOpType::NotEqual, left, right) => Value::Bool(!left.is_equal(&right)),
 
                     (BinOpType::Add, _, _) => {
                         return Err(LoxError::runtime(
@@ -288,14 +448,4 @@ impl Interpreter {
             _ => true,
         }
     }
-
-    fn is_equal(left: Value, right: Value) -> bool {
-        match (&left, &right) {
-            (Value::Nil, Value::Nil) => true,
-            (Value::Nil, _) => false,
-            (Value::Number(left_num), Value::Number(right_num)) => left_num == right_num,
-            (Value::String(left_str), Value::String(right_str)) => left_str == right_str,
-            _ => false,
-        }
-    }
 }
@@ -0,0 +1,15 @@
+pub mod callable;
+pub mod convert;
+pub mod environment;
+pub mod error;
+pub mod expr;
+pub mod function;
+pub mod interpreter;
+pub mod lox;
+pub mod operator;
+pub mod parser;
+pub mod scanner;
+pub mod stmt;
+pub mod token;
+pub mod value;
+
@@ -1,9 +1,11 @@
-use std::{
-    fs::read_to_string,
-    io::{stdin, stdout, ErrorKind, Write},
-};
+use std::{fs::read_to_string, io::ErrorKind};
 
-use crate::{interpreter::Interpreter, parser::Parser, scanner::Scanner};
+use crate::{
+    error::{LoxError, LoxErrorType},
+    interpreter::Interpreter,
+    parser::Parser,
+    scanner::Scanner,
+};
 
 pub struct Lox;
 
@@ -27,19 +29,23 @@ impl Lox {
         Lox::run(code, &mut interpreter);
     }
 
-    pub fn run_prompt() {
-        println!("klox, yet another Lox implementation, Katie Janzen 2023");
-
-        let stdin = stdin();
-        let mut stdout = stdout();
-        let mut interpreter = Interpreter::new();
+    pub fn run_prompt(mut interpreter: Interpreter) {
+        writeln!(
+            interpreter.stdout(),
+            "klox, yet another Lox implementation, Katie Janzen 2023"
+        )
+        .expect("Failed to write to stdout");
 
         loop {
-            print!("> ");
+            let stdout = interpreter.stdout();
+            write!(stdout, "> ").expect("Failed to write to stdout");
             stdout.flush().expect("Failed to flush stdout");
 
             let mut line = String::new();
-            stdin.read_line(&mut line).expect("Failed to read stdin");
+            interpreter
+                .stdin()
+                .read_line(&mut line)
+                .expect("Failed to read stdin");
 
             if line.is_empty() {
                 break;
@@ -49,14 +55,31 @@ impl Lox {
         }
     }
 
-    fn run(source: String, interpreter: &mut Interpreter) {
-        let mut scanner = Scanner::new(source);
+    pub fn run(source: String, interpreter: &mut Interpreter) -> bool {
+        let mut scanner = Scanner::new(source.clone());
 
         let tokens = scanner.scan_tokens();
+        Lox::report(interpreter, scanner.errors(), &source);
 
         let mut parser = Parser::new(tokens);
         let statements = parser.parse();
+        Lox::report(interpreter, parser.errors(), &source);
+
+        let mut ok = scanner.errors().is_empty() && parser.errors().is_empty();
+        if let Err(error) = interpreter.interpret(statements) {
+            if !matches!(error.kind(), LoxErrorType::Return(_)) {
+                Lox::report(interpreter, &[error], &source);
+                ok = false;
+            }
+        }
 
-        interpreter.interpret(statements);
+        ok
+    }
+
+    fn report(interpreter: &mut Interpreter, errors: &[LoxError], source: &str) {
+        let stderr = interpreter.stderr();
+        for error in errors {
+            writeln!(stderr, "{}", error.render(source)).expect("Failed to write to stderr");
+        }
     }
 }
@@ -1,26 +1,17 @@
-pub mod callable;
-pub mod environment;
-pub mod error;
-pub mod expr;
-pub mod function;
-pub mod interpreter;
-pub mod lox;
-pub mod operator;
-pub mod parser;
-pub mod scanner;
-pub mod stmt;
-pub mod token;
-pub mod value;
-
-use lox::*;
+use shared::interrupt::interrupt_on_ctrlc;
 use std::env;
+use treewalk::{interpreter::Interpreter, lox::*};
 
 fn main() {
     let args: Vec<String> = env::args().collect();
     let len = args.len();
 
     match len {
-        1 => Lox::run_prompt(),
+        1 => {
+            let interpreter = Interpreter::new();
+            interrupt_on_ctrlc(interpreter.interrupt_handle());
+            Lox::run_prompt(interpreter)
+        }
         2 => Lox::run_file(args[1].clone()),
         _ => println!("Usage: klox [script]"),
     }
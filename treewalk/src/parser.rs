@@ -3,8 +3,8 @@ use crate::{
     expr::{Expr, ExprType},
     lox::Lox,
     operator::{BinOp, BinOpType, LogOp, LogOpType, UnOp, UnOpType},
-    stmt::Stmt,
-    token::{Token, TokenType},
+    stmt::{Stmt, StmtType},
+    token::{Span, Token, TokenType},
     value::Value,
 };
 
@@ -12,6 +12,7 @@ pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
     eof: Token,
+    errors: Vec<LoxError>,
 }
 
 impl Parser {
@@ -19,10 +20,15 @@ impl Parser {
         Parser {
             tokens,
             current: 0,
-            eof: Token::new(TokenType::Eof, "", Value::Nil, -1),
+            eof: Token::new(TokenType::Eof, "", Value::Nil, Span::new(0, 0, -1, 1)),
+            errors: vec![],
         }
     }
 
+    pub fn errors(&self) -> &[LoxError] {
+        &self.errors
+    }
+
     pub fn parse(&mut self) -> Vec<Stmt> {
         let mut statements: Vec<Stmt> = vec![];
 
@@ -34,6 +40,7 @@ impl Parser {
     }
 
     fn declaration(&mut self) -> Stmt {
+        let start = self.peek().span();
         let result = match self.peek().kind() {
             TokenType::Fun => {
                 self.advance();
@@ -50,14 +57,16 @@ impl Parser {
 
         match result {
             Ok(stmt) => stmt,
-            Err(_) => {
+            Err(error) => {
+                self.errors.push(error);
                 self.sync();
-                Stmt::Empty
+                self.stmt(StmtType::Empty, start)
             }
         }
     }
 
     fn function<S: Into<String>>(&mut self, kind: S) -> Result<Stmt, LoxError> {
+        let start = self.previous().span();
         let kind = kind.into();
         let name = self.consume(TokenType::Identifier, format!("Expect {} name.", kind))?;
 
@@ -69,10 +78,10 @@ impl Parser {
         if !self.check(TokenType::RightParen) {
             loop {
                 if parameters.len() >= Lox::MAX_ARGS {
-                    LoxError::runtime(
+                    self.errors.push(LoxError::runtime(
                         self.peek(),
                         format!("Can't have more than {} parameters.", Lox::MAX_ARGS),
-                    );
+                    ));
                 }
 
                 parameters.push(self.consume(TokenType::Identifier, "Expect parameter name.")?);
@@ -92,7 +101,7 @@ impl Parser {
         )?;
 
         let body = self.block()?;
-        Ok(Stmt::Function(name, parameters, body))
+        Ok(self.stmt(StmtType::Function(name, parameters, body), start))
     }
 
     fn statement(&mut self) -> Result<Stmt, LoxError> {
@@ -135,23 +144,25 @@ impl Parser {
 
     fn return_statement(&mut self) -> Result<Stmt, LoxError> {
         let keyword = self.previous();
+        let start = keyword.span();
         let mut expr = Expr::new();
         if !self.check(TokenType::Semicolon) {
             expr = self.expression()?;
         }
 
         self.consume(TokenType::Semicolon, "Expect ';' after return value.")?;
-        Ok(Stmt::Return(keyword, expr))
+        Ok(self.stmt(StmtType::Return(keyword, expr), start))
     }
 
     fn for_statement(&mut self) -> Result<Stmt, LoxError> {
+        let start = self.previous().span();
         self.consume(TokenType::LeftParen, "Expect '(' after 'for'")?;
 
         // Initializer
         let initializer = match self.peek().kind() {
             TokenType::Semicolon => {
                 self.advance();
-                Stmt::Empty
+                self.stmt(StmtType::Empty, start)
             }
 
             TokenType::Var => {
@@ -179,36 +190,40 @@ impl Parser {
         self.consume(TokenType::RightParen, "Expect ')' after for clauses.")?;
 
         let mut body = self.statement()?;
+        let span = start.to(self.previous().span());
 
         if let ExprType::Empty = increment.kind() {
         } else {
-            body = Stmt::Block(vec![body, Stmt::Expr(increment)]);
+            let increment = Stmt::new(StmtType::Expr(increment), span);
+            body = Stmt::new(StmtType::Block(vec![body, increment]), span);
         }
 
         if let ExprType::Empty = condition.kind() {
-            condition = Expr::literal(Value::Bool(true));
+            condition = Expr::literal(Value::Bool(true), start);
         }
 
-        body = Stmt::While(condition, Box::new(body));
+        body = Stmt::new(StmtType::While(condition, Box::new(body)), span);
 
-        if let Stmt::Empty = initializer {
+        if let StmtType::Empty = initializer.kind() {
         } else {
-            body = Stmt::Block(vec![initializer, body]);
+            body = Stmt::new(StmtType::Block(vec![initializer, body]), span);
         }
 
         Ok(body)
     }
 
     fn while_statment(&mut self) -> Result<Stmt, LoxError> {
+        let start = self.previous().span();
         self.consume(TokenType::LeftParen, "Expect '(' after 'while'.")?;
         let condition = self.expression()?;
         self.consume(TokenType::RightParen, "Expect ')' after condition.")?;
         let body = self.statement()?;
 
-        Ok(Stmt::While(condition, Box::new(body)))
+        Ok(self.stmt(StmtType::While(condition, Box::new(body)), start))
     }
 
     fn if_statement(&mut self) -> Result<Stmt, LoxError> {
+        let start = self.previous().span();
         self.consume(TokenType::LeftParen, "Expect '(' after 'if'.")?;
         let condition = self.expression()?;
         self.consume(TokenType::RightParen, "Expect ')' after if condition.")?;
@@ -217,18 +232,17 @@ impl Parser {
         if let TokenType::Else = self.peek().kind() {
             self.advance();
             let else_branch = self.statement()?;
-            return Ok(Stmt::IfElse(
-                condition,
-                Box::new(then_branch),
-                Box::new(else_branch),
-            ));
+            let kind = StmtType::IfElse(condition, Box::new(then_branch), Box::new(else_branch));
+            return Ok(self.stmt(kind, start));
         }
 
-        Ok(Stmt::If(condition, Box::new(then_branch)))
+        Ok(self.stmt(StmtType::If(condition, Box::new(then_branch)), start))
     }
 
     fn block_statement(&mut self) -> Result<Stmt, LoxError> {
-        Ok(Stmt::Block(self.block()?))
+        let start = self.previous().span();
+        let statements = self.block()?;
+        Ok(self.stmt(StmtType::Block(statements), start))
     }
 
     fn block(&mut self) -> Result<Vec<Stmt>, LoxError> {
@@ -243,6 +257,7 @@ impl Parser {
     }
 
     fn var_declaration(&mut self) -> Result<Stmt, LoxError> {
+        let start = self.previous().span();
         let name = self.consume(TokenType::Identifier, "Expect variable name.")?;
 
         let initializer = if let TokenType::Equal = self.peek().kind() {
@@ -257,19 +272,21 @@ impl Parser {
             "Expect ';' after variable declaration",
         )?;
 
-        Ok(Stmt::Var(name, initializer))
+        Ok(self.stmt(StmtType::Var(name, initializer), start))
     }
 
     fn print_statement(&mut self) -> Result<Stmt, LoxError> {
+        let start = self.previous().span();
         let expr = self.expression()?;
         self.consume(TokenType::Semicolon, "Expect ';' after value.")?;
-        Ok(Stmt::Print(expr))
+        Ok(self.stmt(StmtType::Print(expr), start))
     }
 
     fn expression_statement(&mut self) -> Result<Stmt, LoxError> {
+        let start = self.peek().span();
         let expr = self.expression()?;
         self.consume(TokenType::Semicolon, "Expect ';' after expression.")?;
-        Ok(Stmt::Expr(expr))
+        Ok(self.stmt(StmtType::Expr(expr), start))
     }
 
     fn expression(&mut self) -> Result<Expr, LoxError> {
@@ -288,7 +305,8 @@ impl Parser {
                 return Ok(Expr::assign(name.clone(), value));
             }
 
-            LoxError::syntax(&equals, "Invalid assignment target.");
+            self.errors
+                .push(LoxError::syntax(&equals, "Invalid assignment target."));
             return Ok(Expr::new());
         }
 
@@ -439,10 +457,10 @@ impl Parser {
         if !self.check(TokenType::RightParen) {
             loop {
                 if arguments.len() >= Lox::MAX_ARGS {
-                    LoxError::runtime(
+                    self.errors.push(LoxError::runtime(
                         self.peek(),
                         format!("Can't have more than {} arguments.", Lox::MAX_ARGS),
-                    );
+                    ));
                 }
 
                 arguments.push(self.expression()?);
@@ -464,15 +482,16 @@ impl Parser {
         let token = self.peek();
 
         let expr = match token.kind() {
-            TokenType::False => Expr::literal(Value::Bool(false)),
-            TokenType::True => Expr::literal(Value::Bool(true)),
-            TokenType::Nil => Expr::literal(Value::Nil),
-            TokenType::Number | TokenType::String => Expr::literal(token.literal()),
+            TokenType::False => Expr::literal(Value::Bool(false), token.span()),
+            TokenType::True => Expr::literal(Value::Bool(true), token.span()),
+            TokenType::Nil => Expr::literal(Value::Nil, token.span()),
+            TokenType::Number | TokenType::String => Expr::literal(token.literal(), token.span()),
             TokenType::Identifier => Expr::variable(token.clone()),
             TokenType::LeftParen => {
+                let start = token.span();
                 let expr = self.expression()?;
-                self.consume(TokenType::RightParen, "Expect ')' after expression.")?;
-                Expr::grouping(expr)
+                let paren = self.consume(TokenType::RightParen, "Expect ')' after expression.")?;
+                Expr::grouping(expr, start.to(paren.span()))
             }
 
             _ => return Err(LoxError::syntax(token, "Expect Expression")),
@@ -490,6 +509,10 @@ impl Parser {
         Err(LoxError::syntax(self.peek(), message))
     }
 
+    fn stmt(&self, kind: StmtType, start: Span) -> Stmt {
+        Stmt::new(kind, start.to(self.previous().span()))
+    }
+
     fn check(&self, kind: TokenType) -> bool {
         !self.is_at_end() && self.peek().kind() == kind
     }
@@ -7,9 +7,12 @@ use crate::{
 pub struct Scanner {
     source: Vec<char>,
     tokens: Vec<Token>,
-    start: usize,
+    errors: Vec<LoxError>,
+    start: Span,
     current: usize,
+    offset: usize,
     line: i32,
+    column: usize,
 }
 
 impl Scanner {
@@ -17,23 +20,32 @@ impl Scanner {
         Scanner {
             source: source.chars().collect(),
             tokens: vec![],
-            start: 0,
+            errors: vec![],
+            start: Span::default(),
             current: 0,
+            offset: 0,
             line: 1,
+            column: 1,
         }
     }
 
+    pub fn errors(&self) -> &[LoxError] {
+        &self.errors
+    }
+
     pub fn scan_tokens(&mut self) -> Vec<Token> {
         while !self.is_at_end() {
-            self.start = self.current;
+            self.start = Span::new(self.offset, self.offset, self.line, self.column);
             let c = self.advance();
 
-            if self.create_token(c, self.line).is_err() {
+            if let Err(error) = self.create_token(c) {
+                self.errors.push(error);
                 break;
             }
         }
 
-        let token = Token::new(TokenType::Eof, "", Value::Nil, self.line);
+        self.start = Span::new(self.offset, self.offset, self.line, self.column);
+        let token = Token::new(TokenType::Eof, "", Value::Nil, self.span());
         self.tokens.push(token);
         self.tokens.clone()
     }
@@ -57,22 +69,37 @@ impl Scanner {
     fn advance(&mut self) -> char {
         let c = self.source[self.current];
         self.current += 1;
+        self.offset += c.len_utf8();
+
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+
         c
     }
 
+    fn span(&self) -> Span {
+        Span::new(
+            self.start.start(),
+            self.offset,
+            self.start.line(),
+            self.start.column(),
+        )
+    }
+
     fn string(&mut self) -> Result<(), LoxError> {
         let mut string_value = String::new();
 
         while self.peek() != Some('"') && !self.is_at_end() {
-            if let Some('\n') = self.peek() {
-                self.line += 1;
-            }
             string_value.push(self.advance());
         }
 
         if self.is_at_end() {
             return Err(LoxError::error(
-                self.line,
+                self.span(),
                 "Unterminated string",
                 LoxErrorType::SyntaxError,
             ));
@@ -85,7 +112,7 @@ impl Scanner {
             TokenType::String,
             string_value.clone(),
             Value::String(string_value),
-            self.line,
+            self.span(),
         );
         self.tokens.push(token);
 
@@ -127,7 +154,7 @@ impl Scanner {
             TokenType::Number,
             number_string,
             Value::Number(value),
-            self.line,
+            self.span(),
         );
         self.tokens.push(token);
 
@@ -174,14 +201,14 @@ impl Scanner {
             kind,
             ident_string.clone(),
             Value::Identifier { name: ident_string },
-            self.line,
+            self.span(),
         );
         self.tokens.push(token);
 
         Ok(())
     }
 
-    fn create_token(&mut self, c: char, line: i32) -> Result<(), LoxError> {
+    fn create_token(&mut self, c: char) -> Result<(), LoxError> {
         let literal = Value::Nil;
 
         let kind = match c {
@@ -247,11 +274,6 @@ impl Scanner {
                 }
             }
 
-            '\n' => {
-                self.line += 1;
-                return Ok(());
-            }
-
             _ if c.is_ascii_whitespace() => return Ok(()),
 
             '"' => {
@@ -271,14 +293,14 @@ impl Scanner {
 
             _ => {
                 return Err(LoxError::error(
-                    line,
+                    self.span(),
                     "Unexpected character",
                     LoxErrorType::SyntaxError,
                 ))
             }
         };
 
-        let token = Token::new(kind, c, literal, line);
+        let token = Token::new(kind, c, literal, self.span());
         self.tokens.push(token);
 
         Ok(())
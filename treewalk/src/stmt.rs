@@ -1,7 +1,10 @@
-use crate::{expr::Expr, token::Token};
+use crate::{
+    expr::Expr,
+    token::{Span, Token},
+};
 
 #[derive(Debug, Clone)]
-pub enum Stmt {
+pub enum StmtType {
     Empty,
     Print(Expr),
     Expr(Expr),
@@ -13,3 +16,27 @@ pub enum Stmt {
     Function(Token, Vec<Token>, Vec<Stmt>),
     Return(Token, Expr),
 }
+
+#[derive(Debug, Clone)]
+pub struct Stmt {
+    kind: StmtType,
+    span: Span,
+}
+
+impl Stmt {
+    pub fn new(kind: StmtType, span: Span) -> Self {
+        Stmt { kind, span }
+    }
+
+    pub fn kind(&self) -> &StmtType {
+        &self.kind
+    }
+
+    pub fn into_kind(self) -> StmtType {
+        self.kind
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+}
@@ -1,5 +1,7 @@
 use std::fmt::Display;
 
+use shared::span;
+
 use crate::value::Value;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -59,21 +61,67 @@ impl Display for TokenType {
     }
 }
 
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Span {
+    start: usize,
+    end: usize,
+    line: i32,
+    column: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize, line: i32, column: usize) -> Self {
+        Span {
+            start,
+            end,
+            line,
+            column,
+        }
+    }
+
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    pub fn line(&self) -> i32 {
+        self.line
+    }
+
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            end: other.end.max(self.end),
+            ..self
+        }
+    }
+
+    pub fn underline(&self, source: &str) -> String {
+        span::underline(source, self.start, self.end)
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Token {
     kind: TokenType,
     lexeme: String,
     literal: Value,
-    line: i32,
+    span: Span,
 }
 
 impl Token {
-    pub fn new<S: Into<String>>(kind: TokenType, lexeme: S, literal: Value, line: i32) -> Token {
+    pub fn new<S: Into<String>>(kind: TokenType, lexeme: S, literal: Value, span: Span) -> Token {
         Token {
             kind,
             lexeme: lexeme.into(),
             literal,
-            line,
+            span,
         }
     }
 
@@ -90,7 +138,15 @@ impl Token {
     }
 
     pub fn line(&self) -> i32 {
-        self.line
+        self.span.line
+    }
+
+    pub fn column(&self) -> usize {
+        self.span.column
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
     }
 }
 
@@ -1,4 +1,6 @@
-use std::fmt::Display;
+use std::{convert::Infallible, fmt::Display};
+
+use shared::value::{self, Graph, Operand};
 
 use crate::callable::Callable;
 
@@ -12,6 +14,68 @@ pub enum Value {
     Callable(Box<Callable>),
 }
 
+impl Value {
+    pub fn is_equal(&self, other: &Value) -> bool {
+        value::equal(self.operand(), other.operand())
+    }
+
+    fn operand(&self) -> Operand<'_, &Callable> {
+        match self {
+            Value::Bool(boolean) => Operand::Boolean(*boolean),
+            Value::Identifier { name } | Value::String(name) => Operand::String(name),
+            Value::Number(number) => Operand::Number(*number),
+            Value::Nil => Operand::Nil,
+            Value::Callable(callable) => Operand::Object(callable),
+        }
+    }
+}
+
+/// Values here are cloned rather than shared, and there are no instances to look inside,
+/// so every value is a leaf to `deepEqual`, `freeze` and `deepCopy`
+pub struct Values;
+
+impl Graph for Values {
+    type Value = Value;
+    type Node = Infallible;
+    type Key = Infallible;
+
+    fn equal(&self, a: &Value, b: &Value) -> bool {
+        a.is_equal(b)
+    }
+
+    fn node(&self, _value: &Value) -> Option<Infallible> {
+        None
+    }
+
+    fn value(&self, node: Infallible) -> Value {
+        match node {}
+    }
+
+    fn same_shape(&self, a: Infallible, _b: Infallible) -> bool {
+        match a {}
+    }
+
+    fn fields(&self, node: Infallible) -> Vec<(Infallible, Value)> {
+        match node {}
+    }
+
+    fn field(&self, node: Infallible, _key: Infallible) -> Option<Value> {
+        match node {}
+    }
+
+    fn freeze(&mut self, node: Infallible) -> bool {
+        match node {}
+    }
+
+    fn alloc_like(&mut self, node: Infallible) -> Infallible {
+        match node {}
+    }
+
+    fn set_field(&mut self, node: Infallible, _key: Infallible, _value: Value) {
+        match node {}
+    }
+}
+
 impl Display for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{self:?}")
@@ -0,0 +1,8 @@
+use std::io;
+
+use treewalk::interpreter::Interpreter;
+
+/// An interpreter with its output discarded and nothing to read
+pub fn interpreter() -> Interpreter {
+    Interpreter::with_streams(io::sink(), io::sink(), io::empty())
+}
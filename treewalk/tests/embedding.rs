@@ -0,0 +1,40 @@
+mod common;
+
+use common::interpreter;
+use treewalk::{lox::Lox, value::Value};
+
+#[test]
+fn globals_round_trip() {
+    let mut interpreter = interpreter();
+    interpreter.set_global("width", 3);
+    interpreter.set_global("height", 4.5);
+    interpreter.set_global("name", "box");
+
+    let source = "var area = width * height; var label = name + \"!\";";
+    assert!(Lox::run(source.to_string(), &mut interpreter));
+
+    assert_eq!(interpreter.get_global::<f64>("area").unwrap(), 13.5);
+    assert_eq!(interpreter.get_global::<String>("label").unwrap(), "box!");
+    assert!(interpreter.get_global::<f64>("missing").is_err());
+    assert!(interpreter.get_global::<bool>("area").is_err());
+}
+
+#[test]
+fn call_by_name_and_value() {
+    let mut interpreter = interpreter();
+    let source = "fun greet(greeting, name) { return greeting + \", \" + name; }";
+    assert!(Lox::run(source.to_string(), &mut interpreter));
+
+    let greeting: String = interpreter
+        .call("greet", ("Hello", String::from("Lox")))
+        .unwrap();
+    assert_eq!(greeting, "Hello, Lox");
+
+    let greet: Value = interpreter.get_global("greet").unwrap();
+    let greeting: String = interpreter.call(greet, vec!["Hi", "you"]).unwrap();
+    assert_eq!(greeting, "Hi, you");
+
+    assert!(interpreter.call::<_, _, f64>("clock", ()).is_ok());
+    assert!(interpreter.call::<_, _, f64>("greet", (1,)).is_err());
+    assert!(interpreter.call::<_, _, f64>("nope", ()).is_err());
+}
@@ -0,0 +1,64 @@
+mod common;
+
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use common::interpreter;
+use shared::interrupt::Interrupt;
+use treewalk::{error::LoxErrorType, interpreter::Interpreter, parser::Parser, scanner::Scanner};
+
+fn interpret(interpreter: &mut Interpreter, source: &str) -> Option<Interrupt> {
+    let tokens = Scanner::new(source.to_string()).scan_tokens();
+    let statements = Parser::new(tokens).parse();
+
+    match interpreter.interpret(statements) {
+        Err(error) => match error.kind() {
+            LoxErrorType::Interrupted(interrupt) => Some(*interrupt),
+            _ => None,
+        },
+        Ok(()) => None,
+    }
+}
+
+#[test]
+fn fuel_runs_out() {
+    let mut interpreter = interpreter();
+    interpreter.set_fuel(Some(1000));
+    assert_eq!(
+        interpret(&mut interpreter, "while (true) {}"),
+        Some(Interrupt::Fuel)
+    );
+    assert_eq!(interpreter.fuel(), Some(0));
+}
+
+#[test]
+fn deadline_passes() {
+    let mut interpreter = interpreter();
+    interpreter.set_deadline(Some(Instant::now() + Duration::from_millis(50)));
+    assert_eq!(
+        interpret(&mut interpreter, "while (true) {}"),
+        Some(Interrupt::Deadline)
+    );
+}
+
+#[test]
+fn handle_interrupts_from_another_thread() {
+    let mut interpreter = interpreter();
+    let handle = interpreter.interrupt_handle();
+
+    let interrupter = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        handle.interrupt();
+    });
+
+    assert_eq!(
+        interpret(&mut interpreter, "while (true) {}"),
+        Some(Interrupt::Handle)
+    );
+    interrupter.join().unwrap();
+
+    assert_eq!(interpret(&mut interpreter, "var a = 1;"), None);
+    assert_eq!(interpreter.get_global::<f64>("a").unwrap(), 1.0);
+}
@@ -0,0 +1,33 @@
+use std::io;
+
+use shared::io::Capture;
+use treewalk::{interpreter::Interpreter, lox::Lox};
+
+#[test]
+fn output_goes_to_sinks() {
+    let stdout = Capture::default();
+    let stderr = Capture::default();
+    let mut interpreter = Interpreter::with_streams(stdout.clone(), stderr.clone(), io::empty());
+
+    assert!(Lox::run(
+        String::from("print 1 + 2;\nprint \"lox\";"),
+        &mut interpreter
+    ));
+    assert!(!Lox::run(String::from("print nope;"), &mut interpreter));
+
+    assert_eq!(stdout.contents(), "3\nlox\n");
+    assert!(stderr.contents().contains("Undefined variable 'nope'."));
+}
+
+#[test]
+fn prompt_writes_to_stdout_sink() {
+    let stdout = Capture::default();
+    let interpreter = Interpreter::with_streams(stdout.clone(), io::sink(), &b"print 1;\n"[..]);
+
+    Lox::run_prompt(interpreter);
+
+    assert_eq!(
+        stdout.contents(),
+        "klox, yet another Lox implementation, Katie Janzen 2023\n> 1\n> "
+    );
+}